@@ -82,8 +82,14 @@ fn sphere_shadow_example() {
 					let point = r.position(hit_value.t);
 					let normal = hit_value.object.normal_at(point);
 					let eye = -r.direction;
-					let color =
-						shape.material().lighting(&shape, &light, &point, &eye, &normal, false);
+					let color = shape.material().lighting(
+						&shape,
+						&light,
+						&point,
+						&eye,
+						&normal,
+						Color::new(1.0, 1.0, 1.0),
+					);
 
 					c.write_pixel(x, y, color);
 				},
@@ -115,7 +121,7 @@ fn sphere_scene_example() {
 			rotation_y(-std::f64::consts::PI / 4.0) *
 			rotation_x(std::f64::consts::PI / 2.0),
 	);
-	left_wall.set_material(*floor.material());
+	left_wall.set_material(floor.material().clone());
 	left_wall.get_material().pattern =
 		Some(ColorPattern::new_checker(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0)));
 
@@ -126,7 +132,7 @@ fn sphere_scene_example() {
 			rotation_y(std::f64::consts::PI / 4.0) *
 			rotation_x(std::f64::consts::PI / 2.0),
 	);
-	right_wall.set_material(*floor.material());
+	right_wall.set_material(floor.material().clone());
 
 	let mut middle = Sphere::default();
 	// middle.get_material().pattern = Some(ColorPattern::new_checker(Color::new(1.0, 1.0, 1.0),
@@ -0,0 +1,42 @@
+use crate::{
+	patterns::color_pattern::{ColorPattern, Pattern},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Weighted average of two patterns, `weight` is the share of `b`
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct BlendPattern {
+	pub a: Box<ColorPattern>,
+	pub b: Box<ColorPattern>,
+	pub weight: f64,
+	pub transform: Matrix4D,
+}
+
+impl BlendPattern {
+	pub fn new(a: ColorPattern, b: ColorPattern) -> Self {
+		Self { a: Box::new(a), b: Box::new(b), weight: 0.5, transform: Matrix4D::identity() }
+	}
+
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		self.a.sub_pattern_at(point, shape_uv) * (1.0 - self.weight) +
+			self.b.sub_pattern_at(point, shape_uv) * self.weight
+	}
+}
+
+impl Pattern for BlendPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
@@ -0,0 +1,196 @@
+use crate::primitives::{point::Point, sampler::Sampler};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum DistanceMetric {
+	Euclidean,
+	Manhattan,
+	Chebyshev,
+}
+
+impl DistanceMetric {
+	pub fn distance(&self, dx: f64, dy: f64, dz: f64) -> f64 {
+		match self {
+			Self::Euclidean => (dx * dx + dy * dy + dz * dz).sqrt(),
+			Self::Manhattan => dx.abs() + dy.abs() + dz.abs(),
+			Self::Chebyshev => dx.abs().max(dy.abs()).max(dz.abs()),
+		}
+	}
+}
+
+// Which cellular distance a pattern shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum CellularFeature {
+	// distance to the closest feature point, round cells
+	F1,
+	// distance to the second closest one
+	F2,
+	// zero on the borders between cells, good for cracks and scales
+	F2MinusF1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct CellularSample {
+	pub f1: f64,
+	pub f2: f64,
+	// identifies the cell of the closest feature point
+	pub cell: u64,
+}
+
+impl CellularSample {
+	pub fn feature(&self, feature: CellularFeature) -> f64 {
+		match feature {
+			CellularFeature::F1 => self.f1,
+			CellularFeature::F2 => self.f2,
+			CellularFeature::F2MinusF1 => self.f2 - self.f1,
+		}
+	}
+}
+
+// Worley noise: one feature point scattered in every unit cell, placed from a hash of the cell
+// coordinates and `seed` so the same seed always gives the same cells
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Worley {
+	pub seed: u64,
+	pub metric: DistanceMetric,
+}
+
+impl Worley {
+	pub fn new(seed: u64) -> Self {
+		Self { seed, metric: DistanceMetric::Euclidean }
+	}
+
+	pub fn cell_hash(&self, x: i64, y: i64, z: i64) -> u64 {
+		let mut sampler = Sampler::new(
+			self.seed ^
+				(x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^
+				(y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f) ^
+				(z as u64).wrapping_mul(0x1656_67b1_9e37_79f9),
+		);
+		sampler.next_u64()
+	}
+
+	pub fn feature_point(&self, x: i64, y: i64, z: i64) -> Point {
+		let mut sampler = Sampler::new(self.cell_hash(x, y, z));
+		Point::new(
+			x as f64 + sampler.next_f64(),
+			y as f64 + sampler.next_f64(),
+			z as f64 + sampler.next_f64(),
+		)
+	}
+
+	// Two closest feature points. Cells are visited in growing rings around the cell of `point`
+	// until no cell left can hold a point closer than the second closest one found so far.
+	pub fn sample(&self, point: &Point) -> CellularSample {
+		let p = point.tuple;
+		let (cx, cy, cz) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+		let mut closest = CellularSample { f1: f64::MAX, f2: f64::MAX, cell: 0 };
+		// a cell in ring r is at least r - 1 away along one axis whatever the metric, and points
+		// which are not finite find nothing in their own cell
+		let gap = |c: i64, v: f64| (c as f64 - v).max(v - (c + 1) as f64).max(0.0);
+		let mut r = 0;
+		while r == 0 || (closest.f1 < f64::MAX && ((r - 1) as f64) < closest.f2) {
+			for x in cx - r..=cx + r {
+				for y in cy - r..=cy + r {
+					for z in cz - r..=cz + r {
+						if (x - cx).abs().max((y - cy).abs()).max((z - cz).abs()) != r {
+							continue
+						}
+						// skip cells which lie entirely further away than the second closest point
+						if self.metric.distance(gap(x, p.x), gap(y, p.y), gap(z, p.z)) >= closest.f2
+						{
+							continue
+						}
+						let f = self.feature_point(x, y, z).tuple;
+						let d = self.metric.distance(f.x - p.x, f.y - p.y, f.z - p.z);
+						if d < closest.f1 {
+							closest.f2 = closest.f1;
+							closest.f1 = d;
+							closest.cell = self.cell_hash(x, y, z);
+						} else if d < closest.f2 {
+							closest.f2 = d;
+						}
+					}
+				}
+			}
+			r += 1;
+		}
+		closest
+	}
+}
+
+impl Default for Worley {
+	fn default() -> Self {
+		Self::new(0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn metric_test() {
+		assert_eq!(DistanceMetric::Euclidean.distance(3.0, -4.0, 0.0), 5.0);
+		assert_eq!(DistanceMetric::Manhattan.distance(3.0, -4.0, 1.0), 8.0);
+		assert_eq!(DistanceMetric::Chebyshev.distance(3.0, -4.0, 1.0), 4.0);
+	}
+
+	#[test]
+	fn worley_test() {
+		let worley = Worley::new(5);
+
+		// feature points sit inside their own cell
+		for (x, y, z) in [(0, 0, 0), (-3, 2, 7), (10, -10, 1)] {
+			let f = worley.feature_point(x, y, z).tuple;
+			assert!(f.x >= x as f64 && f.x < x as f64 + 1.0);
+			assert!(f.y >= y as f64 && f.y < y as f64 + 1.0);
+			assert!(f.z >= z as f64 && f.z < z as f64 + 1.0);
+		}
+
+		// F1 vanishes on a feature point and its cell is the point's own cell
+		let f = worley.feature_point(2, 3, 4);
+		let sample = worley.sample(&f);
+		assert_eq!(sample.f1, 0.0);
+		assert_eq!(sample.cell, worley.cell_hash(2, 3, 4));
+		assert!(sample.f2 > 0.0);
+
+		let mut sampler = Sampler::new(1);
+		for metric in
+			[DistanceMetric::Euclidean, DistanceMetric::Manhattan, DistanceMetric::Chebyshev]
+		{
+			let worley = Worley { seed: 5, metric };
+			for _ in 0..100 {
+				let p = Point::new(
+					sampler.next_f64() * 10.0,
+					sampler.next_f64() * 10.0,
+					sampler.next_f64() * 10.0,
+				);
+				let s = worley.sample(&p);
+				assert!(s.f1 <= s.f2);
+				assert!(s.feature(CellularFeature::F2MinusF1) >= 0.0);
+
+				// the same two points a brute force search over a wide neighbourhood finds
+				let (cx, cy, cz) = (p.tuple.x as i64, p.tuple.y as i64, p.tuple.z as i64);
+				let mut distances: Vec<(f64, u64)> = (-4..=4)
+					.flat_map(|x| (-4..=4).flat_map(move |y| (-4..=4).map(move |z| (x, y, z))))
+					.map(|(x, y, z)| {
+						let (x, y, z) = (cx + x, cy + y, cz + z);
+						let f = worley.feature_point(x, y, z) - p;
+						let d = metric.distance(f.tuple.x, f.tuple.y, f.tuple.z);
+						(d, worley.cell_hash(x, y, z))
+					})
+					.collect();
+				distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+				assert_eq!((s.f1, s.cell, s.f2), (distances[0].0, distances[0].1, distances[1].0));
+			}
+		}
+	}
+
+	#[test]
+	fn seed_test() {
+		let p = Point::new(0.3, 4.2, -1.7);
+		assert_eq!(Worley::new(1).sample(&p), Worley::new(1).sample(&p));
+		assert_ne!(Worley::new(1).sample(&p), Worley::new(2).sample(&p));
+		assert_eq!(Worley::new(1).sample(&Point::new(f64::NAN, 0.0, 0.0)).f1, f64::MAX);
+	}
+}
@@ -0,0 +1,42 @@
+use crate::{
+	patterns::{
+		cellular::{CellularFeature, Worley},
+		color_pattern::Pattern,
+	},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Blends from `a` to `b` as the chosen cellular distance goes from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct CellularPattern {
+	pub a: Color,
+	pub b: Color,
+	pub worley: Worley,
+	pub feature: CellularFeature,
+	pub transform: Matrix4D,
+}
+
+impl CellularPattern {
+	pub fn new(a: Color, b: Color, feature: CellularFeature) -> Self {
+		Self { a, b, worley: Worley::default(), feature, transform: Matrix4D::identity() }
+	}
+}
+
+impl Pattern for CellularPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		let t = self.worley.sample(point).feature(self.feature).clamp(0.0, 1.0);
+		self.a * (1.0 - t) + self.b * t
+	}
+}
@@ -0,0 +1,55 @@
+use crate::{
+	patterns::{color_pattern::Pattern, noise::Noise},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Fractal Brownian motion between the sky colour `a` and the cloud colour `b`
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct CloudsPattern {
+	pub a: Color,
+	pub b: Color,
+	pub noise: Noise,
+	pub octaves: usize,
+	// fraction of the sky covered by clouds, between 0 and 1
+	pub coverage: f64,
+	pub transform: Matrix4D,
+}
+
+impl CloudsPattern {
+	pub fn new(a: Color, b: Color) -> Self {
+		Self {
+			a,
+			b,
+			noise: Noise::default(),
+			octaves: 6,
+			coverage: 0.5,
+			transform: Matrix4D::identity(),
+		}
+	}
+}
+
+impl Pattern for CloudsPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		let density = 0.5 + 0.5 * self.noise.fbm(point, self.octaves, 2.0, 0.5);
+		// anything below the threshold is clear sky, the rest fades into full cloud
+		let threshold = 1.0 - self.coverage;
+		let t = if threshold >= 1.0 {
+			0.0
+		} else {
+			((density - threshold) / (1.0 - threshold)).clamp(0.0, 1.0)
+		};
+		self.a * (1.0 - t) + self.b * t
+	}
+}
@@ -1,20 +1,49 @@
 use crate::{
 	patterns::{
-		checker_pattern::CheckerPattern, gradient_pattern::GradientPattern,
-		ring_pattern::RingPattern, stripe_pattern::StripePattern, test_pattern::TestPattern,
+		blend_pattern::BlendPattern,
+		cellular::CellularFeature,
+		cellular_pattern::CellularPattern,
+		checker_pattern::CheckerPattern,
+		clouds_pattern::CloudsPattern,
+		color_ramp::ColorRamp,
+		gradient_pattern::GradientPattern,
+		image_pattern::ImagePattern,
+		marble_pattern::MarblePattern,
+		nested_pattern::{NestedLayout, NestedPattern},
+		perturbed_pattern::PerturbedPattern,
+		ramp_pattern::{RampPattern, RampSource},
+		ring_pattern::RingPattern,
+		stripe_pattern::StripePattern,
+		test_pattern::TestPattern,
+		texture_map::{TextureMap, UvCheckers},
+		uv_mapping::UvMapping,
+		voronoi_pattern::VoronoiPattern,
+		wood_pattern::WoodPattern,
 	},
 	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
 	shapes::shape::ConcreteShape,
+	visualisation::canvas::Canvas,
 };
 use core::fmt::Debug;
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum ColorPattern {
 	TestPattern(TestPattern),
 	StripePattern(StripePattern),
 	GradientPattern(GradientPattern),
 	RingPattern(RingPattern),
 	CheckerPattern(CheckerPattern),
+	TextureMap(TextureMap),
+	ImagePattern(ImagePattern),
+	MarblePattern(MarblePattern),
+	WoodPattern(WoodPattern),
+	CloudsPattern(CloudsPattern),
+	NestedPattern(NestedPattern),
+	BlendPattern(BlendPattern),
+	PerturbedPattern(PerturbedPattern),
+	CellularPattern(CellularPattern),
+	VoronoiPattern(VoronoiPattern),
+	RampPattern(RampPattern),
 }
 
 impl ColorPattern {
@@ -38,13 +67,92 @@ impl ColorPattern {
 		Self::CheckerPattern(CheckerPattern::new(a, b))
 	}
 
-	pub fn pattern_at_object<'a>(&self, object: &'a dyn ConcreteShape, point: &Point) -> Color {
-		let obj_point =
-			object.transform().inverse().expect("Could not invert object transform") * (*point);
+	pub fn new_texture_map(uv_pattern: UvCheckers, mapping: UvMapping) -> Self {
+		Self::TextureMap(TextureMap::new(uv_pattern, mapping))
+	}
+
+	pub fn new_image(canvas: Canvas, mapping: UvMapping) -> Self {
+		Self::ImagePattern(ImagePattern::new(canvas, mapping))
+	}
+
+	pub fn new_marble(a: Color, b: Color) -> Self {
+		Self::MarblePattern(MarblePattern::new(a, b))
+	}
+
+	pub fn new_wood(a: Color, b: Color) -> Self {
+		Self::WoodPattern(WoodPattern::new(a, b))
+	}
+
+	pub fn new_clouds(a: Color, b: Color) -> Self {
+		Self::CloudsPattern(CloudsPattern::new(a, b))
+	}
+
+	pub fn new_nested(layout: NestedLayout, a: ColorPattern, b: ColorPattern) -> Self {
+		Self::NestedPattern(NestedPattern::new(layout, a, b))
+	}
+
+	pub fn new_blend(a: ColorPattern, b: ColorPattern) -> Self {
+		Self::BlendPattern(BlendPattern::new(a, b))
+	}
+
+	pub fn new_perturbed(pattern: ColorPattern, scale: f64) -> Self {
+		Self::PerturbedPattern(PerturbedPattern::new(pattern, scale))
+	}
+
+	pub fn new_cellular(a: Color, b: Color, feature: CellularFeature) -> Self {
+		Self::CellularPattern(CellularPattern::new(a, b, feature))
+	}
+
+	pub fn new_voronoi(colors: Vec<Color>) -> Self {
+		Self::VoronoiPattern(VoronoiPattern::new(colors))
+	}
+
+	pub fn new_ramp(ramp: ColorRamp, source: RampSource) -> Self {
+		Self::RampPattern(RampPattern::new(ramp, source))
+	}
+
+	// Colour of this pattern when used inside another one, `point` is in the parent's pattern
+	// space and goes through this pattern's own transform. `shape_uv` is the object's own uv at
+	// the point, handed down to any `UvMapping::Shape` pattern further in.
+	pub fn sub_pattern_at(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
 		let pattern_point =
-			self.transform().inverse().expect("Could not invert pattern transform") * obj_point;
+			self.transform().inverse().expect("Could not invert pattern transform") * *point;
+		self.pattern_at_uv(&pattern_point, shape_uv)
+	}
+
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		match self {
+			Self::TextureMap(p) => p.pattern_at_uv(point, shape_uv),
+			Self::ImagePattern(p) => p.pattern_at_uv(point, shape_uv),
+			Self::NestedPattern(p) => p.pattern_at_uv(point, shape_uv),
+			Self::BlendPattern(p) => p.pattern_at_uv(point, shape_uv),
+			Self::PerturbedPattern(p) => p.pattern_at_uv(point, shape_uv),
+			Self::RampPattern(p) => p.pattern_at_uv(point, shape_uv),
+			_ => self.pattern_at(point),
+		}
+	}
+
+	// Whether this pattern, or any pattern inside it, samples the shape's own uv
+	pub fn uses_shape_uv(&self) -> bool {
+		match self {
+			Self::TextureMap(p) => p.mapping == UvMapping::Shape,
+			Self::ImagePattern(p) => p.mapping == UvMapping::Shape,
+			Self::NestedPattern(p) => p.a.uses_shape_uv() || p.b.uses_shape_uv(),
+			Self::BlendPattern(p) => p.a.uses_shape_uv() || p.b.uses_shape_uv(),
+			Self::PerturbedPattern(p) => p.pattern.uses_shape_uv(),
+			Self::RampPattern(p) => match &p.source {
+				RampSource::Pattern(pattern) => pattern.uses_shape_uv(),
+				_ => false,
+			},
+			_ => false,
+		}
+	}
 
-		self.pattern_at(&pattern_point)
+	pub fn pattern_at_object(&self, object: &dyn ConcreteShape, point: &Point) -> Color {
+		let obj_point =
+			object.transform().inverse().expect("Could not invert object transform") * (*point);
+		let shape_uv = self.uses_shape_uv().then(|| object.local_uv_at(obj_point));
+		self.sub_pattern_at(&obj_point, shape_uv)
 	}
 }
 
@@ -56,6 +164,17 @@ impl Pattern for ColorPattern {
 			Self::GradientPattern(p) => p.transform(),
 			Self::RingPattern(p) => p.transform(),
 			Self::CheckerPattern(p) => p.transform(),
+			Self::TextureMap(p) => p.transform(),
+			Self::ImagePattern(p) => p.transform(),
+			Self::MarblePattern(p) => p.transform(),
+			Self::WoodPattern(p) => p.transform(),
+			Self::CloudsPattern(p) => p.transform(),
+			Self::NestedPattern(p) => p.transform(),
+			Self::BlendPattern(p) => p.transform(),
+			Self::PerturbedPattern(p) => p.transform(),
+			Self::CellularPattern(p) => p.transform(),
+			Self::VoronoiPattern(p) => p.transform(),
+			Self::RampPattern(p) => p.transform(),
 		}
 	}
 
@@ -66,6 +185,17 @@ impl Pattern for ColorPattern {
 			Self::GradientPattern(p) => p.get_transform(),
 			Self::RingPattern(p) => p.get_transform(),
 			Self::CheckerPattern(p) => p.get_transform(),
+			Self::TextureMap(p) => p.get_transform(),
+			Self::ImagePattern(p) => p.get_transform(),
+			Self::MarblePattern(p) => p.get_transform(),
+			Self::WoodPattern(p) => p.get_transform(),
+			Self::CloudsPattern(p) => p.get_transform(),
+			Self::NestedPattern(p) => p.get_transform(),
+			Self::BlendPattern(p) => p.get_transform(),
+			Self::PerturbedPattern(p) => p.get_transform(),
+			Self::CellularPattern(p) => p.get_transform(),
+			Self::VoronoiPattern(p) => p.get_transform(),
+			Self::RampPattern(p) => p.get_transform(),
 		}
 	}
 
@@ -76,6 +206,17 @@ impl Pattern for ColorPattern {
 			Self::GradientPattern(p) => p.set_transform(transform),
 			Self::RingPattern(p) => p.set_transform(transform),
 			Self::CheckerPattern(p) => p.set_transform(transform),
+			Self::TextureMap(p) => p.set_transform(transform),
+			Self::ImagePattern(p) => p.set_transform(transform),
+			Self::MarblePattern(p) => p.set_transform(transform),
+			Self::WoodPattern(p) => p.set_transform(transform),
+			Self::CloudsPattern(p) => p.set_transform(transform),
+			Self::NestedPattern(p) => p.set_transform(transform),
+			Self::BlendPattern(p) => p.set_transform(transform),
+			Self::PerturbedPattern(p) => p.set_transform(transform),
+			Self::CellularPattern(p) => p.set_transform(transform),
+			Self::VoronoiPattern(p) => p.set_transform(transform),
+			Self::RampPattern(p) => p.set_transform(transform),
 		}
 	}
 
@@ -86,6 +227,17 @@ impl Pattern for ColorPattern {
 			Self::GradientPattern(p) => p.pattern_at(point),
 			Self::RingPattern(p) => p.pattern_at(point),
 			Self::CheckerPattern(p) => p.pattern_at(point),
+			Self::TextureMap(p) => p.pattern_at(point),
+			Self::ImagePattern(p) => p.pattern_at(point),
+			Self::MarblePattern(p) => p.pattern_at(point),
+			Self::WoodPattern(p) => p.pattern_at(point),
+			Self::CloudsPattern(p) => p.pattern_at(point),
+			Self::NestedPattern(p) => p.pattern_at(point),
+			Self::BlendPattern(p) => p.pattern_at(point),
+			Self::PerturbedPattern(p) => p.pattern_at(point),
+			Self::CellularPattern(p) => p.pattern_at(point),
+			Self::VoronoiPattern(p) => p.pattern_at(point),
+			Self::RampPattern(p) => p.pattern_at(point),
 		}
 	}
 }
@@ -100,7 +252,15 @@ pub trait Pattern {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::shapes::{shape::ConcreteShape, spheres::Sphere};
+	use crate::{
+		patterns::{
+			cellular_pattern::CellularPattern, image_pattern::TextureFilter,
+			voronoi_pattern::VoronoiPattern,
+		},
+		shapes::{shape::ConcreteShape, spheres::Sphere},
+		visualisation::materials::Material,
+	};
+	use std::f64::consts::PI;
 
 	#[test]
 	fn test_stripe_pattern() {
@@ -176,6 +336,412 @@ mod tests {
 		assert_eq!(p.pattern_at(&Point::new(0.708, 0.0, 0.708)), black);
 	}
 
+	#[test]
+	fn test_uv_checkers() {
+		let white = Color::new(1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0);
+
+		let checkers = UvCheckers::new(2.0, 2.0, black, white);
+		assert_eq!(checkers.uv_pattern_at(0.0, 0.0), black);
+		assert_eq!(checkers.uv_pattern_at(0.5, 0.0), white);
+		assert_eq!(checkers.uv_pattern_at(0.0, 0.5), white);
+		assert_eq!(checkers.uv_pattern_at(0.5, 0.5), black);
+		assert_eq!(checkers.uv_pattern_at(1.0, 1.0), black);
+
+		// checkers wrapped around a sphere
+		let p = ColorPattern::new_texture_map(
+			UvCheckers::new(16.0, 8.0, black, white),
+			UvMapping::Spherical,
+		);
+		for (point, color) in [
+			(Point::new(0.4315, 0.4670, 0.7719), white),
+			(Point::new(-0.9654, 0.2552, -0.0534), black),
+			(Point::new(0.1039, 0.7090, 0.6975), white),
+			(Point::new(-0.4986, -0.7856, -0.3663), black),
+			(Point::new(-0.0317, -0.9395, 0.3411), black),
+			(Point::new(0.4809, -0.7721, 0.4154), black),
+			(Point::new(0.0285, -0.9612, -0.2745), black),
+			(Point::new(-0.5734, -0.2162, -0.7903), white),
+			(Point::new(0.7688, -0.1470, 0.6223), black),
+			(Point::new(-0.7652, 0.2175, 0.6060), black),
+		] {
+			assert_eq!(p.pattern_at(&point), color);
+		}
+	}
+
+	#[test]
+	fn test_shape_mapping() {
+		use crate::shapes::triangle::Triangle;
+
+		let white = Color::new(1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0);
+		let p = ColorPattern::new_texture_map(
+			UvCheckers::new(2.0, 2.0, black, white),
+			UvMapping::Shape,
+		);
+
+		// barycentric coordinates on a triangle
+		let t = Triangle::new(
+			Point::new(0.0, 0.0, 0.0),
+			Point::new(1.0, 0.0, 0.0),
+			Point::new(0.0, 1.0, 0.0),
+		);
+		assert_eq!(p.pattern_at_object(&t, &Point::new(0.1, 0.1, 0.0)), black);
+		assert_eq!(p.pattern_at_object(&t, &Point::new(0.6, 0.1, 0.0)), white);
+		assert_eq!(p.pattern_at_object(&t, &Point::new(0.1, 0.6, 0.0)), white);
+
+		// looked up without an object, the point is mapped like a plane
+		assert_eq!(p.pattern_at(&Point::new(0.1, 0.0, 0.6)), white);
+
+		// composites hand the triangle's uv down instead of wrapping spherically
+		let blend = ColorPattern::new_blend(p.clone(), p.clone());
+		let nested = ColorPattern::new_nested(NestedLayout::Stripe, blend.clone(), p.clone());
+		for pattern in [blend, nested] {
+			assert_eq!(pattern.pattern_at_object(&t, &Point::new(0.1, 0.1, 0.0)), black);
+			assert_eq!(pattern.pattern_at_object(&t, &Point::new(0.6, 0.1, 0.0)), white);
+			assert_eq!(pattern.pattern_at_object(&t, &Point::new(0.1, 0.6, 0.0)), white);
+		}
+	}
+
+	#[test]
+	fn test_image_pattern() {
+		use crate::shapes::plane::Plane;
+
+		let mut canvas = Canvas::new(2, 1);
+		canvas.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+		let mut p = ColorPattern::new_image(canvas, UvMapping::Shape);
+		if let ColorPattern::ImagePattern(image) = &mut p {
+			image.filter = TextureFilter::Nearest;
+		}
+
+		// planes use the planar mapping of the object space point
+		let plane = Plane::default();
+		assert_eq!(
+			p.pattern_at_object(&plane, &Point::new(0.2, 0.0, 0.5)),
+			Color::new(0.0, 0.0, 0.0)
+		);
+		assert_eq!(
+			p.pattern_at_object(&plane, &Point::new(1.7, 0.0, 0.5)),
+			Color::new(1.0, 1.0, 1.0)
+		);
+
+		// usable as a material pattern
+		let m = Material { pattern: Some(p.clone()), ..Material::default() };
+		assert_eq!(m.color_at(&plane, &Point::new(0.7, 0.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+	}
+
+	#[test]
+	fn test_marble_pattern() {
+		let white = Color::new(1.0, 1.0, 1.0);
+		let black = Color::new(0.0, 0.0, 0.0);
+		let mut p = ColorPattern::new_marble(white, black);
+
+		// without turbulence marble is a sine wave along x
+		if let ColorPattern::MarblePattern(marble) = &mut p {
+			marble.distortion = 0.0;
+		}
+		approx::assert_relative_eq!(
+			p.pattern_at(&Point::new(0.0, 3.0, 1.0)),
+			Color::new(0.5, 0.5, 0.5)
+		);
+		approx::assert_relative_eq!(p.pattern_at(&Point::new(PI / 2.0, 0.0, 0.0)), black);
+		approx::assert_relative_eq!(p.pattern_at(&Point::new(-PI / 2.0, 0.0, 5.0)), white);
+
+		// turbulence bends the veins, and the pattern follows its transform
+		let mut p = ColorPattern::new_marble(white, black);
+		let straight = ColorPattern::new_marble(white, black);
+		let q = Point::new(0.3, 0.7, 0.2);
+		assert_eq!(p.pattern_at(&q), straight.pattern_at(&q));
+		if let ColorPattern::MarblePattern(marble) = &mut p {
+			marble.distortion = 0.0;
+		}
+		assert_ne!(p.pattern_at(&q), straight.pattern_at(&q));
+
+		let s = Sphere::default();
+		let mut p = ColorPattern::new_marble(white, black);
+		p.set_transform(crate::primitives::transformations::scaling(2.0, 2.0, 2.0));
+		assert_eq!(p.pattern_at_object(&s, &Point::new(0.6, 1.4, 0.4)), straight.pattern_at(&q));
+	}
+
+	#[test]
+	fn test_wood_pattern() {
+		let light = Color::new(0.8, 0.6, 0.4);
+		let dark = Color::new(0.4, 0.2, 0.1);
+		let mut p = ColorPattern::new_wood(light, dark);
+		if let ColorPattern::WoodPattern(wood) = &mut p {
+			wood.distortion = 0.0;
+		}
+		// rings depend on the distance from the y axis only
+		assert_eq!(p.pattern_at(&Point::new(0.0, 0.0, 0.0)), light);
+		assert_eq!(
+			p.pattern_at(&Point::new(0.3, 0.0, 0.4)),
+			p.pattern_at(&Point::new(0.0, 7.0, 0.5))
+		);
+		approx::assert_relative_eq!(
+			p.pattern_at(&Point::new(0.125, 0.0, 0.0)),
+			light * 0.875 + dark * 0.125
+		);
+
+		// noise moves the rings around
+		let wobbly = ColorPattern::new_wood(light, dark);
+		let q = Point::new(0.3, 0.45, 0.4);
+		assert_ne!(wobbly.pattern_at(&q), p.pattern_at(&q));
+	}
+
+	#[test]
+	fn test_clouds_pattern() {
+		let sky = Color::new(0.3, 0.5, 0.9);
+		let cloud = Color::new(1.0, 1.0, 1.0);
+		let mut p = ColorPattern::new_clouds(sky, cloud);
+
+		// coverage goes from clear to overcast
+		let mut sampler = crate::primitives::sampler::Sampler::new(9);
+		let points: Vec<Point> = (0..500)
+			.map(|_| {
+				Point::new(sampler.next_f64() * 10.0, sampler.next_f64(), sampler.next_f64() * 10.0)
+			})
+			.collect();
+		let cloudy = |p: &ColorPattern| points.iter().filter(|q| p.pattern_at(q) != sky).count();
+		let half = cloudy(&p);
+		assert!(half > 100 && half < 400, "{}", half);
+
+		for (coverage, expected) in [(0.0, 0), (1.0, points.len())] {
+			if let ColorPattern::CloudsPattern(clouds) = &mut p {
+				clouds.coverage = coverage;
+			}
+			assert_eq!(cloudy(&p), expected);
+		}
+	}
+
+	#[test]
+	fn test_nested_pattern() {
+		use crate::primitives::transformations::*;
+
+		let (red, green) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0));
+		let (white, black) = (Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+
+		// a checker of stripes, the second stripe rotated to run along z
+		let mut rotated = ColorPattern::new_stripe(white, black);
+		rotated.set_transform(rotation_y(PI / 2.0));
+		let p = ColorPattern::new_nested(
+			NestedLayout::Checker,
+			ColorPattern::new_stripe(red, green),
+			rotated,
+		);
+		assert_eq!(p.pattern_at(&Point::new(0.5, 0.0, 0.5)), red);
+		assert_eq!(p.pattern_at(&Point::new(-0.5, 0.0, -0.5)), green);
+		assert_eq!(p.pattern_at(&Point::new(1.5, 0.0, 1.5)), green);
+		// rotated stripes change along z
+		assert_eq!(p.pattern_at(&Point::new(0.5, 0.0, -0.5)), white);
+		assert_eq!(p.pattern_at(&Point::new(-0.5, 0.0, 0.5)), black);
+
+		// nesting goes as deep as needed
+		let deeper =
+			ColorPattern::new_nested(NestedLayout::Stripe, p.clone(), ColorPattern::new_test());
+		assert_eq!(deeper.pattern_at(&Point::new(0.5, 0.0, 0.5)), red);
+		assert_eq!(deeper.pattern_at(&Point::new(1.5, 0.5, 0.25)), Color::new(1.5, 0.5, 0.25));
+
+		// gradients blend their sub-patterns
+		let p = ColorPattern::new_nested(
+			NestedLayout::Gradient,
+			ColorPattern::new_stripe(white, black),
+			ColorPattern::new_ring(red, green),
+		);
+		approx::assert_relative_eq!(
+			p.pattern_at(&Point::new(0.25, 0.0, 0.0)),
+			Color::new(1.0, 0.75, 0.75)
+		);
+	}
+
+	#[test]
+	fn test_blend_pattern() {
+		use crate::primitives::transformations::*;
+
+		let (white, black) = (Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+		let mut crossed = ColorPattern::new_stripe(white, black);
+		crossed.set_transform(rotation_y(PI / 2.0));
+		let mut p = ColorPattern::new_blend(ColorPattern::new_stripe(white, black), crossed);
+
+		assert_eq!(p.pattern_at(&Point::new(0.5, 0.0, -0.5)), white);
+		assert_eq!(p.pattern_at(&Point::new(0.5, 0.0, 0.5)), Color::new(0.5, 0.5, 0.5));
+		assert_eq!(p.pattern_at(&Point::new(-0.5, 0.0, 0.5)), black);
+
+		if let ColorPattern::BlendPattern(blend) = &mut p {
+			blend.weight = 0.25;
+		}
+		assert_eq!(p.pattern_at(&Point::new(0.5, 0.0, 0.5)), Color::new(0.75, 0.75, 0.75));
+	}
+
+	#[test]
+	fn test_perturbed_pattern() {
+		let (white, black) = (Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+
+		// no jitter looks the pattern up unchanged
+		let p = ColorPattern::new_perturbed(ColorPattern::new_test(), 0.0);
+		assert_eq!(p.pattern_at(&Point::new(0.3, 0.4, 0.5)), Color::new(0.3, 0.4, 0.5));
+
+		// jitter moves points by at most `scale` along each axis
+		let p = ColorPattern::new_perturbed(ColorPattern::new_test(), 0.2);
+		let mut moved = 0;
+		for q in [Point::new(0.3, 0.4, 0.5), Point::new(-2.7, 1.1, 3.3), Point::new(5.5, -0.2, 0.9)]
+		{
+			let c = p.pattern_at(&q);
+			let (dx, dy, dz) = (c.red - q.tuple.x, c.green - q.tuple.y, c.blue - q.tuple.z);
+			assert!(dx.abs() <= 0.25 && dy.abs() <= 0.25 && dz.abs() <= 0.25);
+			if dx != 0.0 || dy != 0.0 || dz != 0.0 {
+				moved += 1;
+			}
+		}
+		assert!(moved > 0);
+
+		// wavy stripe edges: some points near an edge switch sides
+		let stripes = ColorPattern::new_stripe(white, black);
+		let p = ColorPattern::new_perturbed(stripes.clone(), 0.3);
+		let switched = (0..100)
+			.map(|i| Point::new(0.9, i as f64 * 0.37, i as f64 * 0.11))
+			.filter(|q| p.pattern_at(q) != stripes.pattern_at(q))
+			.count();
+		assert!(switched > 0 && switched < 100);
+	}
+
+	#[test]
+	fn test_cellular_pattern() {
+		use crate::patterns::cellular::{DistanceMetric, Worley};
+
+		let (white, black) = (Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
+		let worley = Worley::new(3);
+		let centre = worley.feature_point(1, 0, 0);
+		let near = centre + crate::primitives::vector::Vector::new(0.05, 0.0, 0.0);
+
+		// F1 is dark on the feature points and brightens away from them
+		let mut p = CellularPattern::new(black, white, CellularFeature::F1);
+		p.worley = worley;
+		assert_eq!(p.pattern_at(&centre), black);
+		approx::assert_relative_eq!(
+			p.pattern_at(&near),
+			Color::new(0.05, 0.05, 0.05),
+			epsilon = 1e-9
+		);
+
+		// other metrics measure the same offset differently
+		let diagonal = centre + crate::primitives::vector::Vector::new(0.03, 0.04, 0.0);
+		for (metric, expected) in [
+			(DistanceMetric::Euclidean, 0.05),
+			(DistanceMetric::Manhattan, 0.07),
+			(DistanceMetric::Chebyshev, 0.04),
+		] {
+			p.worley.metric = metric;
+			approx::assert_relative_eq!(p.pattern_at(&diagonal).red, expected, epsilon = 1e-9);
+		}
+
+		// F2 - F1 is zero half way between two feature points
+		let other = worley.feature_point(2, 0, 0);
+		let (a, b) = (centre.tuple, other.tuple);
+		let middle = Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0, (a.z + b.z) / 2.0);
+		let mut edges = CellularPattern::new(black, white, CellularFeature::F2MinusF1);
+		edges.worley = worley;
+		let half = worley.metric.distance(b.x - a.x, b.y - a.y, b.z - a.z) / 2.0;
+		// no third feature point is closer to the middle than these two
+		let sample = worley.sample(&middle);
+		approx::assert_relative_eq!(sample.f1, half, epsilon = 1e-9);
+		approx::assert_relative_eq!(sample.f2, half, epsilon = 1e-9);
+		approx::assert_relative_eq!(edges.pattern_at(&middle), black, epsilon = 1e-9);
+	}
+
+	#[test]
+	fn test_voronoi_pattern() {
+		let colors =
+			vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 1.0)];
+		let mut p = VoronoiPattern::new(colors.clone());
+		p.worley.seed = 11;
+
+		// every point of a cell gets the same colour from the palette
+		let centre = p.worley.feature_point(0, 0, 0);
+		let cell = p.worley.cell_hash(0, 0, 0);
+		let expected = colors[(cell % 3) as usize];
+		assert_eq!(p.pattern_at(&centre), expected);
+		let near = centre + crate::primitives::vector::Vector::new(0.01, -0.01, 0.01);
+		assert_eq!(p.pattern_at(&near), expected);
+
+		// across many cells all colours show up
+		let mut seen = [false; 3];
+		for i in 0..50 {
+			let c = p.pattern_at(&Point::new(i as f64 * 1.3, 0.5, i as f64 * 0.7));
+			seen[colors.iter().position(|x| *x == c).unwrap()] = true;
+		}
+		assert!(seen.iter().all(|s| *s));
+
+		// wide borders cover everything
+		p.border_width = 10.0;
+		assert_eq!(p.pattern_at(&centre), Color::new(0.0, 0.0, 0.0));
+
+		let p = ColorPattern::new_voronoi(colors);
+		assert!(matches!(p, ColorPattern::VoronoiPattern(_)));
+	}
+
+	#[test]
+	fn test_ramp_pattern() {
+		use crate::{
+			patterns::{
+				color_ramp::{ColorStop, RampInterpolation},
+				noise::Noise,
+			},
+			primitives::transformations::scaling,
+		};
+
+		let black = Color::new(0.0, 0.0, 0.0);
+		let white = Color::new(1.0, 1.0, 1.0);
+		let ramp = ColorRamp::new(
+			vec![ColorStop::new(0.0, black), ColorStop::new(1.0, white)],
+			RampInterpolation::Linear,
+		);
+		let grey = |v: f64| Color::new(v, v, v);
+
+		let linear = ColorPattern::new_ramp(ramp.clone(), RampSource::Linear);
+		approx::assert_relative_eq!(linear.pattern_at(&Point::new(0.25, 3.0, -2.0)), grey(0.25));
+		assert_eq!(linear.pattern_at(&Point::new(1.5, 0.0, 0.0)), white);
+
+		let radial = ColorPattern::new_ramp(ramp.clone(), RampSource::Radial);
+		approx::assert_relative_eq!(radial.pattern_at(&Point::new(0.3, 5.0, 0.4)), grey(0.5));
+
+		let spherical = ColorPattern::new_ramp(ramp.clone(), RampSource::Spherical);
+		approx::assert_relative_eq!(spherical.pattern_at(&Point::new(0.2, 0.4, 0.4)), grey(0.6));
+
+		// noise drives the ramp through its whole range
+		let noise = ColorPattern::new_ramp(
+			ramp.clone(),
+			RampSource::Noise { noise: Noise::default(), octaves: 1 },
+		);
+		assert_eq!(noise.pattern_at(&Point::new(1.0, 2.0, 3.0)), grey(0.5));
+		let p = Point::new(0.4, 1.3, -0.7);
+		approx::assert_relative_eq!(
+			noise.pattern_at(&p),
+			grey(0.5 + 0.5 * Noise::default().noise(&p))
+		);
+
+		// another pattern drives it through its luminance and its own transform
+		let mut stripes = ColorPattern::new_stripe(white, black);
+		stripes.set_transform(scaling(2.0, 1.0, 1.0));
+		let driven = ColorPattern::new_ramp(
+			ColorRamp::new(
+				vec![
+					ColorStop::new(0.0, Color::new(1.0, 0.0, 0.0)),
+					ColorStop::new(1.0, Color::new(0.0, 0.0, 1.0)),
+				],
+				RampInterpolation::Linear,
+			),
+			RampSource::Pattern(Box::new(stripes)),
+		);
+		approx::assert_relative_eq!(
+			driven.pattern_at(&Point::new(1.5, 0.0, 0.0)),
+			Color::new(0.0, 0.0, 1.0)
+		);
+		approx::assert_relative_eq!(
+			driven.pattern_at(&Point::new(2.5, 0.0, 0.0)),
+			Color::new(1.0, 0.0, 0.0)
+		);
+	}
+
 	#[test]
 	fn test_checker_pattern() {
 		let white = Color::new(0.0, 0.0, 0.0);
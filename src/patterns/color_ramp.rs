@@ -0,0 +1,148 @@
+use crate::primitives::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum RampInterpolation {
+	// holds the colour of the stop on the left until the next stop
+	Constant,
+	Linear,
+	// eases in and out of every stop
+	Smoothstep,
+	// Catmull-Rom spline through the stops, smooth across them
+	Cubic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ColorStop {
+	pub position: f64,
+	pub color: Color,
+}
+
+impl ColorStop {
+	pub fn new(position: f64, color: Color) -> Self {
+		Self { position, color }
+	}
+}
+
+// Maps a scalar onto colours through any number of stops. Values before the first or after the
+// last stop take the colour of that stop.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ColorRamp {
+	stops: Vec<ColorStop>,
+	pub interpolation: RampInterpolation,
+}
+
+impl ColorRamp {
+	pub fn new(stops: Vec<ColorStop>, interpolation: RampInterpolation) -> Self {
+		let mut ramp = Self { stops: vec![], interpolation };
+		for stop in stops {
+			ramp.add_stop(stop);
+		}
+		ramp
+	}
+
+	pub fn stops(&self) -> &[ColorStop] {
+		&self.stops
+	}
+
+	// Keeps the stops sorted, a stop at the same position as an existing one goes after it
+	pub fn add_stop(&mut self, stop: ColorStop) {
+		let index = self.stops.partition_point(|s| s.position <= stop.position);
+		self.stops.insert(index, stop);
+	}
+
+	pub fn color_at(&self, t: f64) -> Color {
+		let stops = &self.stops;
+		let (first, last) = match (stops.first(), stops.last()) {
+			(Some(first), Some(last)) => (first, last),
+			_ => return Color::new(0.0, 0.0, 0.0),
+		};
+		// NaN would fall between the stops without lying after any of them
+		if t.is_nan() || t <= first.position {
+			return first.color
+		}
+		if t >= last.position {
+			return last.color
+		}
+
+		let i = stops.partition_point(|s| s.position <= t) - 1;
+		let (left, right) = (stops[i], stops[i + 1]);
+		let s = (t - left.position) / (right.position - left.position);
+		match self.interpolation {
+			RampInterpolation::Constant => left.color,
+			RampInterpolation::Linear => left.color + (right.color - left.color) * s,
+			RampInterpolation::Smoothstep =>
+				left.color + (right.color - left.color) * (s * s * (3.0 - 2.0 * s)),
+			RampInterpolation::Cubic => {
+				let before = stops[i.saturating_sub(1)].color;
+				let after = stops[(i + 2).min(stops.len() - 1)].color;
+				catmull_rom(before, left.color, right.color, after, s)
+			},
+		}
+	}
+}
+
+fn catmull_rom(p0: Color, p1: Color, p2: Color, p3: Color, s: f64) -> Color {
+	let (s2, s3) = (s * s, s * s * s);
+	(p1 * 2.0 +
+		(p2 - p0) * s +
+		(p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * s2 +
+		(p1 * 3.0 - p0 - p2 * 3.0 + p3) * s3) *
+		0.5
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ramp(interpolation: RampInterpolation) -> ColorRamp {
+		ColorRamp::new(
+			vec![
+				ColorStop::new(1.0, Color::new(0.0, 0.0, 1.0)),
+				ColorStop::new(0.0, Color::new(0.0, 0.0, 0.0)),
+				ColorStop::new(0.5, Color::new(1.0, 0.0, 0.0)),
+			],
+			interpolation,
+		)
+	}
+
+	#[test]
+	fn ramp_test() {
+		let black = Color::new(0.0, 0.0, 0.0);
+		let red = Color::new(1.0, 0.0, 0.0);
+		let blue = Color::new(0.0, 0.0, 1.0);
+
+		// stops are sorted and the ends are held
+		let linear = ramp(RampInterpolation::Linear);
+		let positions: Vec<f64> = linear.stops().iter().map(|s| s.position).collect();
+		assert_eq!(positions, vec![0.0, 0.5, 1.0]);
+		assert_eq!(linear.color_at(-1.0), black);
+		assert_eq!(linear.color_at(2.0), blue);
+		assert_eq!(linear.color_at(f64::NAN), black);
+
+		// every mode passes through the stops
+		for interpolation in [
+			RampInterpolation::Constant,
+			RampInterpolation::Linear,
+			RampInterpolation::Smoothstep,
+			RampInterpolation::Cubic,
+		] {
+			let ramp = ramp(interpolation);
+			assert_eq!(ramp.color_at(0.0), black);
+			approx::assert_relative_eq!(ramp.color_at(0.5), red);
+			assert_eq!(ramp.color_at(1.0), blue);
+		}
+
+		approx::assert_relative_eq!(linear.color_at(0.25), Color::new(0.5, 0.0, 0.0));
+		approx::assert_relative_eq!(linear.color_at(0.75), Color::new(0.5, 0.0, 0.5));
+		assert_eq!(ramp(RampInterpolation::Constant).color_at(0.75), red);
+		approx::assert_relative_eq!(
+			ramp(RampInterpolation::Smoothstep).color_at(0.125),
+			Color::new(0.15625, 0.0, 0.0)
+		);
+		// the spline overshoots past the red stop instead of turning sharply
+		let cubic = ramp(RampInterpolation::Cubic);
+		approx::assert_relative_eq!(cubic.color_at(0.25), Color::new(0.5625, 0.0, -0.0625));
+
+		assert_eq!(ColorRamp::new(vec![], RampInterpolation::Linear).color_at(0.5), black);
+	}
+}
@@ -0,0 +1,185 @@
+use crate::{
+	patterns::{color_pattern::Pattern, uv_mapping::UvMapping},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+	visualisation::canvas::Canvas,
+};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum TextureFilter {
+	Nearest,
+	Bilinear,
+}
+
+// What happens to texture coordinates outside of [0, 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum TextureAddressing {
+	Wrap,
+	Clamp,
+	Mirror,
+}
+
+impl TextureAddressing {
+	// Brings a texel index into the [0, size) range
+	pub fn texel(&self, index: i64, size: usize) -> usize {
+		let size = size as i64;
+		let index = match self {
+			Self::Wrap => index.rem_euclid(size),
+			Self::Clamp => index.clamp(0, size - 1),
+			Self::Mirror => {
+				let m = index.rem_euclid(2 * size);
+				if m < size {
+					m
+				} else {
+					2 * size - 1 - m
+				}
+			},
+		};
+		index as usize
+	}
+}
+
+// Samples an image at the uv coordinates of the point. The image is shared, so copies of a
+// material do not duplicate the pixels.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct ImagePattern {
+	pub canvas: Arc<Canvas>,
+	pub filter: TextureFilter,
+	pub addressing: TextureAddressing,
+	pub mapping: UvMapping,
+	pub transform: Matrix4D,
+}
+
+impl ImagePattern {
+	pub fn new(canvas: Canvas, mapping: UvMapping) -> Self {
+		assert!(
+			canvas.width > 0 && canvas.height > 0,
+			"Image pattern needs at least one pixel, got {}x{}",
+			canvas.width,
+			canvas.height
+		);
+		Self {
+			canvas: Arc::new(canvas),
+			filter: TextureFilter::Bilinear,
+			addressing: TextureAddressing::Wrap,
+			mapping,
+			transform: Matrix4D::identity(),
+		}
+	}
+
+	// `shape_uv` is the object's own uv at the point, see `UvMapping::map`
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		let (u, v) = self.mapping.map(point, shape_uv);
+		self.uv_pattern_at(u, v)
+	}
+
+	fn texel(&self, x: i64, y: i64) -> Color {
+		self.canvas.pixel_at(
+			self.addressing.texel(x, self.canvas.width),
+			self.addressing.texel(y, self.canvas.height),
+		)
+	}
+
+	// v grows upwards while canvas rows go down, texel centres sit at half integer coordinates
+	pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+		let x = u * self.canvas.width as f64 - 0.5;
+		let y = (1.0 - v) * self.canvas.height as f64 - 0.5;
+		match self.filter {
+			TextureFilter::Nearest => self.texel(x.round() as i64, y.round() as i64),
+			TextureFilter::Bilinear => {
+				let (x0, y0) = (x.floor(), y.floor());
+				let (tx, ty) = (x - x0, y - y0);
+				let (x0, y0) = (x0 as i64, y0 as i64);
+				let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1, y0) * tx;
+				let bottom = self.texel(x0, y0 + 1) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1) * tx;
+				top * (1.0 - ty) + bottom * ty
+			},
+		}
+	}
+}
+
+impl Pattern for ImagePattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// 2x2 image: red green on the top row, blue white on the bottom one
+	fn image() -> Canvas {
+		let mut c = Canvas::new(2, 2);
+		c.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+		c.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+		c.write_pixel(0, 1, Color::new(0.0, 0.0, 1.0));
+		c.write_pixel(1, 1, Color::new(1.0, 1.0, 1.0));
+		c
+	}
+
+	#[test]
+	fn addressing_test() {
+		for (index, wrap, clamp, mirror) in
+			[(0, 0, 0, 0), (3, 3, 3, 3), (4, 0, 3, 3), (5, 1, 3, 2), (-1, 3, 0, 0), (-2, 2, 0, 1)]
+		{
+			assert_eq!(TextureAddressing::Wrap.texel(index, 4), wrap);
+			assert_eq!(TextureAddressing::Clamp.texel(index, 4), clamp);
+			assert_eq!(TextureAddressing::Mirror.texel(index, 4), mirror);
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "Image pattern needs at least one pixel")]
+	fn empty_image_test() {
+		ImagePattern::new(Canvas::new(0, 2), UvMapping::Planar);
+	}
+
+	#[test]
+	fn nearest_test() {
+		let mut p = ImagePattern::new(image(), UvMapping::Planar);
+		p.filter = TextureFilter::Nearest;
+		assert_eq!(p.uv_pattern_at(0.2, 0.8), Color::new(1.0, 0.0, 0.0));
+		assert_eq!(p.uv_pattern_at(0.7, 0.8), Color::new(0.0, 1.0, 0.0));
+		assert_eq!(p.uv_pattern_at(0.2, 0.3), Color::new(0.0, 0.0, 1.0));
+		assert_eq!(p.uv_pattern_at(0.7, 0.3), Color::new(1.0, 1.0, 1.0));
+
+		// planar mapping goes through the point's x and z
+		assert_eq!(p.pattern_at(&Point::new(1.2, 0.0, -0.2)), Color::new(1.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn bilinear_test() {
+		let mut p = ImagePattern::new(image(), UvMapping::Planar);
+		p.addressing = TextureAddressing::Clamp;
+
+		// texel centres return the exact pixel
+		assert_eq!(p.uv_pattern_at(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+		assert_eq!(p.uv_pattern_at(0.75, 0.25), Color::new(1.0, 1.0, 1.0));
+
+		// the middle of the image averages all four
+		assert_eq!(p.uv_pattern_at(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+
+		// halfway along the top row
+		assert_eq!(p.uv_pattern_at(0.5, 0.75), Color::new(0.5, 0.5, 0.0));
+
+		// clamped edges do not blend with the opposite side
+		assert_eq!(p.uv_pattern_at(0.0, 0.75), Color::new(1.0, 0.0, 0.0));
+
+		// wrapped ones do
+		p.addressing = TextureAddressing::Wrap;
+		assert_eq!(p.uv_pattern_at(0.0, 0.75), Color::new(0.5, 0.5, 0.0));
+	}
+}
@@ -0,0 +1,53 @@
+use crate::{
+	patterns::{color_pattern::Pattern, noise::Noise},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Veins running across x, bent by turbulence
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct MarblePattern {
+	pub a: Color,
+	pub b: Color,
+	pub noise: Noise,
+	// veins per unit length along x, divided by PI
+	pub frequency: f64,
+	// how far the turbulence pushes the veins around
+	pub distortion: f64,
+	pub octaves: usize,
+	pub transform: Matrix4D,
+}
+
+impl MarblePattern {
+	pub fn new(a: Color, b: Color) -> Self {
+		Self {
+			a,
+			b,
+			noise: Noise::default(),
+			frequency: 1.0,
+			distortion: 5.0,
+			octaves: 6,
+			transform: Matrix4D::identity(),
+		}
+	}
+}
+
+impl Pattern for MarblePattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		let turbulence = self.noise.turbulence(point, self.octaves);
+		let phase = self.frequency * point.tuple.x + self.distortion * turbulence;
+		let t = 0.5 + 0.5 * phase.sin();
+		self.a * (1.0 - t) + self.b * t
+	}
+}
@@ -1,6 +1,21 @@
+pub mod blend_pattern;
+pub mod cellular;
+pub mod cellular_pattern;
 pub mod checker_pattern;
+pub mod clouds_pattern;
 pub mod color_pattern;
+pub mod color_ramp;
 pub mod gradient_pattern;
+pub mod image_pattern;
+pub mod marble_pattern;
+pub mod nested_pattern;
+pub mod noise;
+pub mod perturbed_pattern;
+pub mod ramp_pattern;
 pub mod ring_pattern;
 pub mod stripe_pattern;
 pub mod test_pattern;
+pub mod texture_map;
+pub mod uv_mapping;
+pub mod voronoi_pattern;
+pub mod wood_pattern;
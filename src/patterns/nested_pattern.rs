@@ -0,0 +1,65 @@
+use crate::{
+	patterns::color_pattern::{ColorPattern, Pattern},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// How a nested pattern splits space between its two sub-patterns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum NestedLayout {
+	Stripe,
+	Checker,
+	Ring,
+	// blends from `a` to `b` along x, repeating every unit
+	Gradient,
+}
+
+// Same layouts as the two colour patterns, with a pattern in place of each colour
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct NestedPattern {
+	pub layout: NestedLayout,
+	pub a: Box<ColorPattern>,
+	pub b: Box<ColorPattern>,
+	pub transform: Matrix4D,
+}
+
+impl NestedPattern {
+	pub fn new(layout: NestedLayout, a: ColorPattern, b: ColorPattern) -> Self {
+		Self { layout, a: Box::new(a), b: Box::new(b), transform: Matrix4D::identity() }
+	}
+
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		let p = point.tuple;
+		let condition = match self.layout {
+			NestedLayout::Stripe => p.x.floor() as i64 % 2,
+			NestedLayout::Checker => (p.x.floor() + p.y.floor() + p.z.floor()) as i64 % 2,
+			NestedLayout::Ring => (p.x * p.x + p.z * p.z).sqrt().floor() as i64 % 2,
+			NestedLayout::Gradient => {
+				let fraction = p.x - p.x.floor();
+				return self.a.sub_pattern_at(point, shape_uv) * (1.0 - fraction) +
+					self.b.sub_pattern_at(point, shape_uv) * fraction
+			},
+		};
+		match condition {
+			0 => self.a.sub_pattern_at(point, shape_uv),
+			_ => self.b.sub_pattern_at(point, shape_uv),
+		}
+	}
+}
+
+impl Pattern for NestedPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
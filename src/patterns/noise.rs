@@ -0,0 +1,302 @@
+use crate::primitives::{point::Point, sampler::Sampler};
+
+// Ken Perlin's improved gradient noise with a permutation shuffled from `seed`, values lie
+// roughly in [-1, 1] and vanish on the integer lattice
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Perlin {
+	pub seed: u64,
+	permutation: Vec<usize>,
+}
+
+impl Perlin {
+	pub fn new(seed: u64) -> Self {
+		Self { seed, permutation: permutation(seed) }
+	}
+
+	pub fn noise(&self, point: &Point) -> f64 {
+		let p = point.tuple;
+		let (xf, yf, zf) = (p.x.floor(), p.y.floor(), p.z.floor());
+		let (x, y, z) = (p.x - xf, p.y - yf, p.z - zf);
+		let cell = |c: f64| (c as i64).rem_euclid(256) as usize;
+		let (xi, yi, zi) = (cell(xf), cell(yf), cell(zf));
+		let (u, v, w) = (fade(x), fade(y), fade(z));
+
+		let perm = &self.permutation;
+		let a = perm[xi] + yi;
+		let (aa, ab) = (perm[a] + zi, perm[a + 1] + zi);
+		let b = perm[xi + 1] + yi;
+		let (ba, bb) = (perm[b] + zi, perm[b + 1] + zi);
+
+		lerp(
+			w,
+			lerp(
+				v,
+				lerp(u, grad(perm[aa], x, y, z), grad(perm[ba], x - 1.0, y, z)),
+				lerp(u, grad(perm[ab], x, y - 1.0, z), grad(perm[bb], x - 1.0, y - 1.0, z)),
+			),
+			lerp(
+				v,
+				lerp(u, grad(perm[aa + 1], x, y, z - 1.0), grad(perm[ba + 1], x - 1.0, y, z - 1.0)),
+				lerp(
+					u,
+					grad(perm[ab + 1], x, y - 1.0, z - 1.0),
+					grad(perm[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+				),
+			),
+		)
+	}
+}
+
+impl Default for Perlin {
+	fn default() -> Self {
+		Self::new(0)
+	}
+}
+
+// Gradient noise summed over the corners of the simplex (tetrahedron) containing the point,
+// cheaper than Perlin noise and without its axis aligned artefacts. Values lie in [-1, 1].
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Simplex {
+	pub seed: u64,
+	permutation: Vec<usize>,
+}
+
+// skewing factors between the cubic and the simplex grid
+const SKEW: f64 = 1.0 / 3.0;
+const UNSKEW: f64 = 1.0 / 6.0;
+// brings the sum of the corner contributions into [-1, 1]
+const SIMPLEX_SCALE: f64 = 76.0;
+
+impl Simplex {
+	pub fn new(seed: u64) -> Self {
+		Self { seed, permutation: permutation(seed) }
+	}
+
+	pub fn noise(&self, point: &Point) -> f64 {
+		let p = point.tuple;
+		let s = (p.x + p.y + p.z) * SKEW;
+		let (i, j, k) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+		let t = (i + j + k) * UNSKEW;
+		let x0 = [p.x - (i - t), p.y - (j - t), p.z - (k - t)];
+
+		// the cube is split into six tetrahedra, ordered by which coordinate is largest
+		let (second, third) = match (x0[0] >= x0[1], x0[1] >= x0[2], x0[0] >= x0[2]) {
+			(true, true, _) => ([1, 0, 0], [1, 1, 0]),
+			(true, false, true) => ([1, 0, 0], [1, 0, 1]),
+			(true, false, false) => ([0, 0, 1], [1, 0, 1]),
+			(false, false, _) => ([0, 0, 1], [0, 1, 1]),
+			(false, true, false) => ([0, 1, 0], [0, 1, 1]),
+			(false, true, true) => ([0, 1, 0], [1, 1, 0]),
+		};
+
+		let cell = |c: f64| (c as i64).rem_euclid(256) as usize;
+		let (ii, jj, kk) = (cell(i), cell(j), cell(k));
+		let perm = &self.permutation;
+		let mut total = 0.0;
+		for (n, offset) in [[0, 0, 0], second, third, [1, 1, 1]].iter().enumerate() {
+			let corner = [
+				x0[0] - offset[0] as f64 + n as f64 * UNSKEW,
+				x0[1] - offset[1] as f64 + n as f64 * UNSKEW,
+				x0[2] - offset[2] as f64 + n as f64 * UNSKEW,
+			];
+			let falloff =
+				0.5 - corner[0] * corner[0] - corner[1] * corner[1] - corner[2] * corner[2];
+			if falloff > 0.0 {
+				let hash = perm[ii + offset[0] + perm[jj + offset[1] + perm[kk + offset[2]]]];
+				total += falloff.powi(4) * grad(hash % 12, corner[0], corner[1], corner[2]);
+			}
+		}
+		SIMPLEX_SCALE * total
+	}
+}
+
+impl Default for Simplex {
+	fn default() -> Self {
+		Self::new(0)
+	}
+}
+
+// Either kind of gradient noise, with fractal sums built on top of it
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Noise {
+	Perlin(Perlin),
+	Simplex(Simplex),
+}
+
+impl Default for Noise {
+	fn default() -> Self {
+		Self::Perlin(Perlin::default())
+	}
+}
+
+impl Noise {
+	pub fn new_perlin(seed: u64) -> Self {
+		Self::Perlin(Perlin::new(seed))
+	}
+
+	pub fn new_simplex(seed: u64) -> Self {
+		Self::Simplex(Simplex::new(seed))
+	}
+
+	pub fn noise(&self, point: &Point) -> f64 {
+		match self {
+			Self::Perlin(n) => n.noise(point),
+			Self::Simplex(n) => n.noise(point),
+		}
+	}
+
+	// Fractal Brownian motion: `octaves` layers of noise, each `lacunarity` times finer and
+	// `gain` times weaker than the previous one, normalised back into [-1, 1]
+	pub fn fbm(&self, point: &Point, octaves: usize, lacunarity: f64, gain: f64) -> f64 {
+		let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, 1.0);
+		for _ in 0..octaves {
+			sum += amplitude * self.noise(&scale(point, frequency));
+			total += amplitude;
+			amplitude *= gain;
+			frequency *= lacunarity;
+		}
+		if total > 0.0 {
+			sum / total
+		} else {
+			0.0
+		}
+	}
+
+	// Like `fbm` with the usual lacunarity of 2 and gain of 0.5, but summing absolute values,
+	// which gives creases where the noise crosses zero. Values lie in [0, 1].
+	pub fn turbulence(&self, point: &Point, octaves: usize) -> f64 {
+		let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, 1.0);
+		for _ in 0..octaves {
+			sum += amplitude * self.noise(&scale(point, frequency)).abs();
+			total += amplitude;
+			amplitude *= 0.5;
+			frequency *= 2.0;
+		}
+		if total > 0.0 {
+			sum / total
+		} else {
+			0.0
+		}
+	}
+}
+
+fn scale(point: &Point, factor: f64) -> Point {
+	let p = point.tuple;
+	Point::new(p.x * factor, p.y * factor, p.z * factor)
+}
+
+// Shuffled 0..256, doubled so lookups of hash + 1 never need wrapping
+fn permutation(seed: u64) -> Vec<usize> {
+	let mut permutation: Vec<usize> = (0..256).collect();
+	let mut sampler = Sampler::new(seed);
+	for i in (1..256).rev() {
+		let j = (sampler.next_u64() % (i as u64 + 1)) as usize;
+		permutation.swap(i, j);
+	}
+	permutation.extend_from_within(..);
+	permutation
+}
+
+fn fade(t: f64) -> f64 {
+	t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+	a + t * (b - a)
+}
+
+// dot product with one of twelve gradients pointing to the edges of a cube
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+	let h = hash & 15;
+	let u = if h < 8 { x } else { y };
+	let v = match h {
+		0..=3 => y,
+		12 | 14 => x,
+		_ => z,
+	};
+	(if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::vector::Vector;
+
+	#[test]
+	fn perlin_test() {
+		let perlin = Perlin::default();
+
+		// zero on the lattice
+		for p in [Point::new(0.0, 0.0, 0.0), Point::new(3.0, -2.0, 7.0), Point::new(-1.0, 5.0, 2.0)]
+		{
+			assert_eq!(perlin.noise(&p), 0.0);
+		}
+
+		// bounded, continuous and not constant
+		let mut sampler = Sampler::new(1);
+		let (mut min, mut max) = (f64::MAX, f64::MIN);
+		for _ in 0..10000 {
+			let p = Point::new(
+				sampler.next_f64() * 20.0 - 10.0,
+				sampler.next_f64() * 20.0 - 10.0,
+				sampler.next_f64() * 20.0 - 10.0,
+			);
+			let n = perlin.noise(&p);
+			let nearby = perlin.noise(&(p + Vector::new(1e-4, 0.0, 0.0)));
+			assert!((n - nearby).abs() < 1e-3);
+			min = min.min(n);
+			max = max.max(n);
+		}
+		assert!(min >= -1.1 && max <= 1.1);
+		assert!(min < -0.4 && max > 0.4);
+	}
+
+	#[test]
+	fn simplex_test() {
+		let simplex = Simplex::default();
+		let mut sampler = Sampler::new(2);
+		let (mut min, mut max) = (f64::MAX, f64::MIN);
+		for _ in 0..20000 {
+			let p = Point::new(
+				sampler.next_f64() * 20.0 - 10.0,
+				sampler.next_f64() * 20.0 - 10.0,
+				sampler.next_f64() * 20.0 - 10.0,
+			);
+			let n = simplex.noise(&p);
+			let nearby = simplex.noise(&(p + Vector::new(1e-4, 1e-4, 0.0)));
+			assert!((n - nearby).abs() < 1e-2);
+			min = min.min(n);
+			max = max.max(n);
+		}
+		assert!(min >= -1.0 && max <= 1.0, "{} {}", min, max);
+		assert!(min < -0.5 && max > 0.5, "{} {}", min, max);
+	}
+
+	#[test]
+	fn fractal_test() {
+		let p = Point::new(0.3, 1.7, -2.2);
+		for noise in [Noise::new_perlin(3), Noise::new_simplex(3)] {
+			// a single octave is the noise itself
+			approx::assert_relative_eq!(noise.fbm(&p, 1, 2.0, 0.5), noise.noise(&p));
+			approx::assert_relative_eq!(noise.turbulence(&p, 1), noise.noise(&p).abs());
+
+			// more octaves add detail but stay in range
+			let fbm = noise.fbm(&p, 5, 2.0, 0.5);
+			assert_ne!(fbm, noise.noise(&p));
+			assert!(fbm.abs() <= 1.0);
+			let turbulence = noise.turbulence(&p, 5);
+			assert!((0.0..=1.0).contains(&turbulence));
+
+			assert_eq!(noise.fbm(&p, 0, 2.0, 0.5), 0.0);
+		}
+	}
+
+	#[test]
+	fn seed_test() {
+		let p = Point::new(1.3, 2.7, -0.4);
+		assert_eq!(Perlin::new(7).noise(&p), Perlin::new(7).noise(&p));
+		assert_ne!(Perlin::new(7).noise(&p), Perlin::new(8).noise(&p));
+		assert_eq!(Simplex::new(7).noise(&p), Simplex::new(7).noise(&p));
+		assert_ne!(Simplex::new(7).noise(&p), Simplex::new(8).noise(&p));
+	}
+}
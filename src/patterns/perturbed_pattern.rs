@@ -0,0 +1,57 @@
+use crate::{
+	patterns::{
+		color_pattern::{ColorPattern, Pattern},
+		noise::Noise,
+	},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point, vector::Vector},
+};
+
+// Looks another pattern up at a point jittered by noise, which makes straight edges wavy
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct PerturbedPattern {
+	pub pattern: Box<ColorPattern>,
+	pub noise: Noise,
+	// largest distance a point is moved along each axis
+	pub scale: f64,
+	pub transform: Matrix4D,
+}
+
+impl PerturbedPattern {
+	pub fn new(pattern: ColorPattern, scale: f64) -> Self {
+		Self {
+			pattern: Box::new(pattern),
+			noise: Noise::default(),
+			scale,
+			transform: Matrix4D::identity(),
+		}
+	}
+
+	// Offsets the three noise lookups so every axis gets an independent jitter
+	pub fn jitter(&self, point: &Point) -> Point {
+		let offset = |dx: f64| self.noise.noise(&(*point + Vector::new(dx, dx, dx)));
+		*point + Vector::new(offset(0.0), offset(17.3), offset(-31.7)) * self.scale
+	}
+
+	// the shape's uv is not jittered, only the point is
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		self.pattern.sub_pattern_at(&self.jitter(point), shape_uv)
+	}
+}
+
+impl Pattern for PerturbedPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
@@ -0,0 +1,71 @@
+use crate::{
+	patterns::{
+		color_pattern::{ColorPattern, Pattern},
+		color_ramp::ColorRamp,
+		noise::Noise,
+	},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Scalar that picks the colour from the ramp
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum RampSource {
+	// x, like `GradientPattern` but without repeating
+	Linear,
+	// distance from the y axis
+	Radial,
+	// distance from the origin
+	Spherical,
+	// fractal noise brought into [0, 1]
+	Noise { noise: Noise, octaves: usize },
+	// luminance of another pattern
+	Pattern(Box<ColorPattern>),
+}
+
+impl RampSource {
+	pub fn value_at(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> f64 {
+		let p = point.tuple;
+		match self {
+			Self::Linear => p.x,
+			Self::Radial => (p.x * p.x + p.z * p.z).sqrt(),
+			Self::Spherical => (p.x * p.x + p.y * p.y + p.z * p.z).sqrt(),
+			Self::Noise { noise, octaves } => 0.5 + 0.5 * noise.fbm(point, *octaves, 2.0, 0.5),
+			Self::Pattern(pattern) => pattern.sub_pattern_at(point, shape_uv).luminance(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct RampPattern {
+	pub ramp: ColorRamp,
+	pub source: RampSource,
+	pub transform: Matrix4D,
+}
+
+impl RampPattern {
+	pub fn new(ramp: ColorRamp, source: RampSource) -> Self {
+		Self { ramp, source, transform: Matrix4D::identity() }
+	}
+
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		self.ramp.color_at(self.source.value_at(point, shape_uv))
+	}
+}
+
+impl Pattern for RampPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
@@ -0,0 +1,65 @@
+use crate::{
+	patterns::{color_pattern::Pattern, uv_mapping::UvMapping},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// 2D checkers in texture space, `width` by `height` squares over the unit square
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct UvCheckers {
+	pub width: f64,
+	pub height: f64,
+	pub a: Color,
+	pub b: Color,
+}
+
+impl UvCheckers {
+	pub fn new(width: f64, height: f64, a: Color, b: Color) -> Self {
+		Self { width, height, a, b }
+	}
+
+	pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+		let condition = ((u * self.width).floor() + (v * self.height).floor()) as i64 % 2;
+		match condition {
+			0 => self.a,
+			_ => self.b,
+		}
+	}
+}
+
+// Wraps a 2D pattern around an object through one of the uv mappings
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct TextureMap {
+	pub uv_pattern: UvCheckers,
+	pub mapping: UvMapping,
+	pub transform: Matrix4D,
+}
+
+impl TextureMap {
+	pub fn new(uv_pattern: UvCheckers, mapping: UvMapping) -> Self {
+		Self { uv_pattern, mapping, transform: Matrix4D::identity() }
+	}
+
+	// `shape_uv` is the object's own uv at the point, see `UvMapping::map`
+	pub fn pattern_at_uv(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> Color {
+		let (u, v) = self.mapping.map(point, shape_uv);
+		self.uv_pattern.uv_pattern_at(u, v)
+	}
+}
+
+impl Pattern for TextureMap {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		self.pattern_at_uv(point, None)
+	}
+}
@@ -0,0 +1,226 @@
+use crate::primitives::point::Point;
+use std::f64::consts::PI;
+
+// Ways of flattening a 3D point into (u, v) texture coordinates, both in [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum UvMapping {
+	Spherical,
+	Planar,
+	Cylindrical,
+	// six faces of the [-1, 1] cube laid out as a horizontal cross, see `cube_map`
+	Cube,
+	// the shape's own parameterisation, e.g. barycentric coordinates on triangles
+	Shape,
+}
+
+impl UvMapping {
+	// `shape_uv` is the shape's own uv at the point, which only `Shape` uses. Without an object to
+	// provide it, e.g. through `Pattern::pattern_at`, `Shape` maps the point like `Planar`.
+	pub fn map(&self, point: &Point, shape_uv: Option<(f64, f64)>) -> (f64, f64) {
+		match self {
+			Self::Shape => shape_uv.unwrap_or_else(|| planar_map(point)),
+			Self::Spherical => spherical_map(point),
+			Self::Planar => planar_map(point),
+			Self::Cylindrical => cylindrical_map(point),
+			Self::Cube => cube_map(point),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum CubeFace {
+	Left,
+	Right,
+	Front,
+	Back,
+	Up,
+	Down,
+}
+
+pub fn spherical_map(point: &Point) -> (f64, f64) {
+	let p = point.tuple;
+	let theta = p.x.atan2(p.z);
+	let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+	let phi = (p.y / radius).clamp(-1.0, 1.0).acos();
+	let raw_u = theta / (2.0 * PI);
+	// flip u so it grows counter-clockwise when viewed from above
+	(1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+pub fn planar_map(point: &Point) -> (f64, f64) {
+	(point.tuple.x.rem_euclid(1.0), point.tuple.z.rem_euclid(1.0))
+}
+
+pub fn cylindrical_map(point: &Point) -> (f64, f64) {
+	let theta = point.tuple.x.atan2(point.tuple.z);
+	let raw_u = theta / (2.0 * PI);
+	(1.0 - (raw_u + 0.5), point.tuple.y.rem_euclid(1.0))
+}
+
+pub fn cube_face(point: &Point) -> CubeFace {
+	let p = point.tuple;
+	let coord = p.x.abs().max(p.y.abs()).max(p.z.abs());
+	match coord {
+		c if c == p.x => CubeFace::Right,
+		c if c == -p.x => CubeFace::Left,
+		c if c == p.y => CubeFace::Up,
+		c if c == -p.y => CubeFace::Down,
+		c if c == p.z => CubeFace::Front,
+		_ => CubeFace::Back,
+	}
+}
+
+// (u, v) inside a single face of the [-1, 1] cube
+pub fn cube_face_uv(face: CubeFace, point: &Point) -> (f64, f64) {
+	let p = point.tuple;
+	let (u, v) = match face {
+		CubeFace::Front => (p.x + 1.0, p.y + 1.0),
+		CubeFace::Back => (1.0 - p.x, p.y + 1.0),
+		CubeFace::Left => (p.z + 1.0, p.y + 1.0),
+		CubeFace::Right => (1.0 - p.z, p.y + 1.0),
+		CubeFace::Up => (p.x + 1.0, 1.0 - p.z),
+		CubeFace::Down => (p.x + 1.0, p.z + 1.0),
+	};
+	(u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+}
+
+// Maps all six faces into a single texture laid out as a horizontal cross:
+//
+//        | up    |
+// left   | front | right | back
+//        | down  |
+pub fn cube_map(point: &Point) -> (f64, f64) {
+	let face = cube_face(point);
+	let (u, v) = cube_face_uv(face, point);
+	let (column, row) = match face {
+		CubeFace::Left => (0.0, 1.0),
+		CubeFace::Front => (1.0, 1.0),
+		CubeFace::Right => (2.0, 1.0),
+		CubeFace::Back => (3.0, 1.0),
+		CubeFace::Up => (1.0, 2.0),
+		CubeFace::Down => (1.0, 0.0),
+	};
+	((column + u) / 4.0, (row + v) / 3.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn spherical_map_test() {
+		for (p, u, v) in [
+			(Point::new(0.0, 0.0, -1.0), 0.0, 0.5),
+			(Point::new(1.0, 0.0, 0.0), 0.25, 0.5),
+			(Point::new(0.0, 0.0, 1.0), 0.5, 0.5),
+			(Point::new(-1.0, 0.0, 0.0), 0.75, 0.5),
+			(Point::new(0.0, 1.0, 0.0), 0.5, 1.0),
+			(Point::new(0.0, -1.0, 0.0), 0.5, 0.0),
+			(Point::new(2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0, 0.0), 0.25, 0.75),
+		] {
+			let (mu, mv) = spherical_map(&p);
+			approx::assert_relative_eq!(mu, u, epsilon = 1e-9);
+			approx::assert_relative_eq!(mv, v, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn shape_map_test() {
+		let p = Point::new(1.25, 0.0, -0.25);
+		assert_eq!(UvMapping::Shape.map(&p, Some((0.1, 0.9))), (0.1, 0.9));
+		// no shape to ask, so the point is mapped like a plane
+		assert_eq!(UvMapping::Shape.map(&p, None), planar_map(&p));
+	}
+
+	#[test]
+	fn planar_map_test() {
+		for (p, u, v) in [
+			(Point::new(0.25, 0.0, 0.5), 0.25, 0.5),
+			(Point::new(0.25, 0.0, -0.25), 0.25, 0.75),
+			(Point::new(0.25, 0.5, -0.25), 0.25, 0.75),
+			(Point::new(1.25, 0.0, 0.5), 0.25, 0.5),
+			(Point::new(0.25, 0.0, -1.75), 0.25, 0.25),
+			(Point::new(1.0, 0.0, -1.0), 0.0, 0.0),
+			(Point::new(0.0, 0.0, 0.0), 0.0, 0.0),
+		] {
+			let (mu, mv) = planar_map(&p);
+			approx::assert_relative_eq!(mu, u, epsilon = 1e-9);
+			approx::assert_relative_eq!(mv, v, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn cylindrical_map_test() {
+		let s = 2.0_f64.sqrt() / 2.0;
+		for (p, u, v) in [
+			(Point::new(0.0, 0.0, -1.0), 0.0, 0.0),
+			(Point::new(0.0, 0.5, -1.0), 0.0, 0.5),
+			(Point::new(0.0, 1.0, -1.0), 0.0, 0.0),
+			(Point::new(s, 0.5, -s), 0.125, 0.5),
+			(Point::new(1.0, 0.5, 0.0), 0.25, 0.5),
+			(Point::new(s, 0.5, s), 0.375, 0.5),
+			(Point::new(0.0, -0.25, 1.0), 0.5, 0.75),
+			(Point::new(-s, 0.5, s), 0.625, 0.5),
+			(Point::new(-1.0, 1.25, 0.0), 0.75, 0.25),
+			(Point::new(-s, 0.5, -s), 0.875, 0.5),
+		] {
+			let (mu, mv) = cylindrical_map(&p);
+			approx::assert_relative_eq!(mu, u, epsilon = 1e-9);
+			approx::assert_relative_eq!(mv, v, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn cube_face_test() {
+		for (p, face) in [
+			(Point::new(-1.0, 0.5, -0.25), CubeFace::Left),
+			(Point::new(1.1, -0.75, 0.8), CubeFace::Right),
+			(Point::new(0.1, 0.6, 0.9), CubeFace::Front),
+			(Point::new(-0.7, 0.0, -2.0), CubeFace::Back),
+			(Point::new(0.5, 1.0, 0.9), CubeFace::Up),
+			(Point::new(-0.2, -1.3, 1.1), CubeFace::Down),
+		] {
+			assert_eq!(cube_face(&p), face);
+		}
+
+		for (face, p, u, v) in [
+			(CubeFace::Front, Point::new(-0.5, 0.5, 1.0), 0.25, 0.75),
+			(CubeFace::Front, Point::new(0.5, -0.5, 1.0), 0.75, 0.25),
+			(CubeFace::Back, Point::new(0.5, 0.5, -1.0), 0.25, 0.75),
+			(CubeFace::Back, Point::new(-0.5, -0.5, -1.0), 0.75, 0.25),
+			(CubeFace::Left, Point::new(-1.0, 0.5, -0.5), 0.25, 0.75),
+			(CubeFace::Left, Point::new(-1.0, -0.5, 0.5), 0.75, 0.25),
+			(CubeFace::Right, Point::new(1.0, 0.5, 0.5), 0.25, 0.75),
+			(CubeFace::Right, Point::new(1.0, -0.5, -0.5), 0.75, 0.25),
+			(CubeFace::Up, Point::new(-0.5, 1.0, -0.5), 0.25, 0.75),
+			(CubeFace::Up, Point::new(0.5, 1.0, 0.5), 0.75, 0.25),
+			(CubeFace::Down, Point::new(-0.5, -1.0, 0.5), 0.25, 0.75),
+			(CubeFace::Down, Point::new(0.5, -1.0, -0.5), 0.75, 0.25),
+		] {
+			let (mu, mv) = cube_face_uv(face, &p);
+			approx::assert_relative_eq!(mu, u, epsilon = 1e-9);
+			approx::assert_relative_eq!(mv, v, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn cube_map_test() {
+		// front face sits in the middle of the cross
+		let (u, v) = cube_map(&Point::new(-0.5, 0.5, 1.0));
+		approx::assert_relative_eq!(u, 1.25 / 4.0);
+		approx::assert_relative_eq!(v, 1.75 / 3.0);
+
+		// back face is the last one in the middle row
+		let (u, v) = cube_map(&Point::new(-0.5, -0.5, -1.0));
+		approx::assert_relative_eq!(u, 3.75 / 4.0);
+		approx::assert_relative_eq!(v, 1.25 / 3.0);
+
+		// up and down are above and below the front face
+		let (u, v) = cube_map(&Point::new(0.5, 1.0, 0.5));
+		approx::assert_relative_eq!(u, 1.75 / 4.0);
+		approx::assert_relative_eq!(v, 2.25 / 3.0);
+		let (u, v) = cube_map(&Point::new(0.5, -1.0, -0.5));
+		approx::assert_relative_eq!(u, 1.75 / 4.0);
+		approx::assert_relative_eq!(v, 0.25 / 3.0);
+	}
+}
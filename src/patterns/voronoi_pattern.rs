@@ -0,0 +1,49 @@
+use crate::{
+	patterns::{cellular::Worley, color_pattern::Pattern},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Flat colour per Worley cell, picked from `colors` by the cell hash, with optional borders
+// wherever the two closest cells are within `border_width` of each other
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct VoronoiPattern {
+	pub colors: Vec<Color>,
+	pub worley: Worley,
+	pub border: Color,
+	pub border_width: f64,
+	pub transform: Matrix4D,
+}
+
+impl VoronoiPattern {
+	pub fn new(colors: Vec<Color>) -> Self {
+		Self {
+			colors,
+			worley: Worley::default(),
+			border: Color::new(0.0, 0.0, 0.0),
+			border_width: 0.0,
+			transform: Matrix4D::identity(),
+		}
+	}
+}
+
+impl Pattern for VoronoiPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		let sample = self.worley.sample(point);
+		if sample.f2 - sample.f1 < self.border_width || self.colors.is_empty() {
+			return self.border
+		}
+		self.colors[(sample.cell % self.colors.len() as u64) as usize]
+	}
+}
@@ -0,0 +1,52 @@
+use crate::{
+	patterns::{color_pattern::Pattern, noise::Noise},
+	primitives::{color::Color, matrix::matrix4d::Matrix4D, point::Point},
+};
+
+// Growth rings around the y axis, wobbled by noise
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct WoodPattern {
+	pub a: Color,
+	pub b: Color,
+	pub noise: Noise,
+	// rings per unit of distance from the axis
+	pub rings: f64,
+	// how far the noise moves the rings, in units of distance
+	pub distortion: f64,
+	pub transform: Matrix4D,
+}
+
+impl WoodPattern {
+	pub fn new(a: Color, b: Color) -> Self {
+		Self {
+			a,
+			b,
+			noise: Noise::default(),
+			rings: 4.0,
+			distortion: 0.1,
+			transform: Matrix4D::identity(),
+		}
+	}
+}
+
+impl Pattern for WoodPattern {
+	fn transform(&self) -> &Matrix4D {
+		&self.transform
+	}
+
+	fn get_transform(&mut self) -> &mut Matrix4D {
+		&mut self.transform
+	}
+
+	fn set_transform(&mut self, transform: Matrix4D) {
+		self.transform = transform
+	}
+
+	fn pattern_at(&self, point: &Point) -> Color {
+		let p = point.tuple;
+		let distance = (p.x * p.x + p.z * p.z).sqrt() + self.distortion * self.noise.noise(point);
+		// sharp edge where the dark late wood meets the next ring
+		let t = (distance * self.rings).rem_euclid(1.0).powi(3);
+		self.a * (1.0 - t) + self.b * t
+	}
+}
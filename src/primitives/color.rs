@@ -23,6 +23,11 @@ impl Color {
 		let blue = num::clamp(self.blue * max, min, max);
 		(red as u32, green as u32, blue as u32)
 	}
+
+	// Relative luminance with Rec. 709 primaries
+	pub fn luminance(&self) -> f64 {
+		0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+	}
 }
 
 impl Add for Color {
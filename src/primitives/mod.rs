@@ -3,6 +3,7 @@ pub mod matrix;
 pub mod point;
 pub mod projectile;
 pub mod ray;
+pub mod sampler;
 pub mod transformations;
 pub mod tuple;
 pub mod vector;
@@ -8,11 +8,18 @@ use crate::{
 pub struct Ray {
 	pub origin: Point,
 	pub direction: Vector,
+	// wavelength in nanometres carried by rays split up by dispersive materials, `None` for rays
+	// carrying all three colour channels
+	pub wavelength: Option<f64>,
 }
 
 impl<'a, 'b> Ray {
 	pub fn new(origin: Point, direction: Vector) -> Self {
-		Ray { origin, direction }
+		Ray { origin, direction, wavelength: None }
+	}
+
+	pub fn with_wavelength(self, wavelength: Option<f64>) -> Self {
+		Ray { wavelength, ..self }
 	}
 
 	pub fn position(&self, t: f64) -> Point {
@@ -45,20 +52,38 @@ impl<'a, 'b> Ray {
 			normal = -normal;
 		}
 
+		// points are offset along the geometric normal so bumps never cause self intersection
+		let geometric_normal = normal;
+		if let Some(bump) = &intersection.object.material().bump {
+			let uv = intersection.uv.unwrap_or_else(|| intersection.object.uv_at(point));
+			normal = bump.perturb(intersection.object, &point, &normal, uv);
+		}
+
 		let reflection_vector = self.direction.reflect(normal);
 
 		let mut n1 = 1.0;
 		let mut n2 = 1.0;
+		let mut medium = None;
+		let mut next_medium = None;
+		let mut medium_entry = 0.0;
 
 		match xs {
 			Some(intersections) => {
 				let mut container: Vec<&dyn ConcreteShape> = vec![];
+				// the medium before the hit is entered at the boundary crossed just before it
+				let mut previous = 0.0_f64;
 				for i in intersections {
 					if intersection == i {
+						medium_entry = previous.max(0.0);
 						if container.len() == 0 {
 							n1 = 1.0;
 						} else {
-							n1 = container.last().unwrap().material().refractive_index;
+							n1 = container
+								.last()
+								.unwrap()
+								.material()
+								.refractive_index_at(self.wavelength);
+							medium = container.last().copied();
 						}
 					}
 
@@ -73,33 +98,51 @@ impl<'a, 'b> Ray {
 						if container.len() == 0 {
 							n2 = 1.0;
 						} else {
-							n2 = container.last().unwrap().material().refractive_index;
+							n2 = container
+								.last()
+								.unwrap()
+								.material()
+								.refractive_index_at(self.wavelength);
+							next_medium = container.last().copied();
 						}
 						break
 					}
+					previous = i.t;
 				}
 			},
 
-			None => n2 = intersection.object.material().refractive_index,
+			None => {
+				n2 = intersection.object.material().refractive_index_at(self.wavelength);
+				next_medium = Some(intersection.object);
+			},
 		}
 
 		IntersectionComputations {
 			t: intersection.t,
 			object: intersection.object,
 			point,
-			over_point: point + normal * 1e-6,
-			under_point: point - normal * 1e-6,
+			over_point: point + geometric_normal * 1e-6,
+			under_point: point - geometric_normal * 1e-6,
 			eye,
 			normal,
 			reflection_vector,
 			inside,
 			n1,
 			n2,
+			uv: intersection.uv,
+			medium,
+			medium_entry,
+			next_medium,
+			wavelength: self.wavelength,
 		}
 	}
 
 	pub fn transform(&self, transformation: Matrix4D) -> Self {
-		Ray { origin: transformation * self.origin, direction: transformation * self.direction }
+		Ray {
+			origin: transformation * self.origin,
+			direction: transformation * self.direction,
+			wavelength: self.wavelength,
+		}
 	}
 }
 
@@ -107,7 +150,7 @@ impl<'a, 'b> Ray {
 mod tests {
 	use super::*;
 	use crate::{
-		primitives::transformations::*,
+		primitives::{color::Color, transformations::*},
 		shapes::{plane::Plane, shape::ConcreteShape, spheres::Sphere},
 	};
 
@@ -197,6 +240,62 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn bump_test() {
+		use crate::visualisation::bump::Bump;
+
+		let mut p = Plane::default();
+		p.get_material().bump = Some(Bump::new_ripples(0.1, 2.0));
+		let r = Ray::new(Point::new(1.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+		let xs = p.intersects(&r).unwrap();
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+
+		// shading and reflection use the bumped normal
+		let slope = 0.1 * 2.0 * 2.0_f64.cos();
+		let normal = Vector::new(-slope, 1.0, 0.0).normalise();
+		approx::assert_relative_eq!(comps.normal, normal, epsilon = 1e-6);
+		approx::assert_relative_eq!(
+			comps.reflection_vector,
+			r.direction.reflect(normal),
+			epsilon = 1e-6
+		);
+
+		// offset points stay on either side of the real surface
+		approx::assert_relative_eq!(comps.over_point, Point::new(1.0, 1e-6, 0.0));
+		approx::assert_relative_eq!(comps.under_point, Point::new(1.0, -1e-6, 0.0));
+	}
+
+	#[test]
+	fn uv_test() {
+		use crate::shapes::triangle::Triangle;
+
+		// uv carried by the intersection
+		let t = Triangle::new(
+			Point::new(0.0, 1.0, 0.0),
+			Point::new(-1.0, 0.0, 0.0),
+			Point::new(1.0, 0.0, 0.0),
+		);
+		let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = t.intersects(&r).unwrap();
+		assert_eq!(xs.len(), 1);
+		let (u, v) = xs[0].uv.unwrap();
+		approx::assert_relative_eq!(u, 0.45, epsilon = 1e-9);
+		approx::assert_relative_eq!(v, 0.25, epsilon = 1e-9);
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+		assert_eq!(comps.surface_uv(), (u, v));
+
+		// uv computed from the object space point
+		let mut s = Sphere::default();
+		s.set_transform(scaling(2.0, 2.0, 2.0));
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = s.intersects(&r).unwrap();
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+		assert_eq!(comps.uv, None);
+		let (u, v) = comps.surface_uv();
+		approx::assert_relative_eq!(u, 0.0, epsilon = 1e-9);
+		approx::assert_relative_eq!(v, 0.5, epsilon = 1e-9);
+	}
+
 	#[test]
 	fn refraction_test() {
 		let mut a = Sphere::new_glass_sphere();
@@ -245,4 +344,26 @@ mod tests {
 		assert_eq!(comps.n1, 1.5);
 		assert_eq!(comps.n2, 1.0);
 	}
+
+	#[test]
+	fn absorption_distance_test() {
+		let mut s = Sphere::new_glass_sphere();
+		s.get_material().absorption = Color::new(0.5, 0.0, 0.0);
+		let absorbed = |distance: f64| Color::new((-0.5 * distance).exp(), 1.0, 1.0);
+
+		// a ray starting inside is only absorbed on the way from its origin to the surface
+		let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0));
+		let xs = s.intersects(&r).unwrap();
+		let comps = r.prepare_computations(&xs[1], Some(&xs));
+		approx::assert_relative_eq!(comps.transmittance(), absorbed(1.0));
+
+		// and one starting outside only from where it entered
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = s.intersects(&r).unwrap();
+		let comps = r.prepare_computations(&xs[1], Some(&xs));
+		assert_eq!(comps.medium_entry, 4.0);
+		approx::assert_relative_eq!(comps.transmittance(), absorbed(2.0));
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+		assert_eq!(comps.transmittance(), Color::new(1.0, 1.0, 1.0));
+	}
 }
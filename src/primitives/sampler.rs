@@ -0,0 +1,123 @@
+use crate::primitives::vector::Vector;
+use std::f64::consts::PI;
+
+// SplitMix64 generator: tiny, fast and good enough for Monte Carlo sampling. It is seeded
+// explicitly so stochastic renders are reproducible.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+	state: u64,
+}
+
+impl Sampler {
+	pub fn new(seed: u64) -> Self {
+		Self { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	// uniform value in [0, 1)
+	pub fn next_f64(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	pub fn next_2d(&mut self) -> (f64, f64) {
+		(self.next_f64(), self.next_f64())
+	}
+}
+
+// Builds two unit vectors which together with `normal` form an orthonormal basis.
+// reference: https://graphics.pixar.com/library/OrthonormalB/paper.pdf
+pub fn orthonormal_basis(normal: &Vector) -> (Vector, Vector) {
+	let n = normal.tuple;
+	let sign = 1.0_f64.copysign(n.z);
+	let a = -1.0 / (sign + n.z);
+	let b = n.x * n.y * a;
+	let tangent = Vector::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+	let bitangent = Vector::new(b, sign + n.y * n.y * a, -n.y);
+	(tangent, bitangent)
+}
+
+// Cosine weighted direction around `normal`, pdf = cos(theta) / PI
+pub fn cosine_sample_hemisphere(normal: &Vector, u1: f64, u2: f64) -> Vector {
+	let r = u1.sqrt();
+	let phi = 2.0 * PI * u2;
+	let (tangent, bitangent) = orthonormal_basis(normal);
+	let z = (1.0 - u1).max(0.0).sqrt();
+	(tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + *normal * z).normalise()
+}
+
+// Uniformly distributed direction on the unit sphere, pdf = 1 / (4 * PI)
+pub fn uniform_sample_sphere(u1: f64, u2: f64) -> Vector {
+	let z = 1.0 - 2.0 * u1;
+	let r = (1.0 - z * z).max(0.0).sqrt();
+	let phi = 2.0 * PI * u2;
+	Vector::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sampler_test() {
+		// same seed produces the same sequence
+		let mut s1 = Sampler::new(42);
+		let mut s2 = Sampler::new(42);
+		for _ in 0..100 {
+			assert_eq!(s1.next_u64(), s2.next_u64());
+		}
+
+		// values stay in [0, 1) and average to 1/2
+		let mut s = Sampler::new(7);
+		let n = 10000;
+		let mut sum = 0.0;
+		for _ in 0..n {
+			let value = s.next_f64();
+			assert!((0.0..1.0).contains(&value));
+			sum += value;
+		}
+		approx::assert_relative_eq!(sum / n as f64, 0.5, epsilon = 1e-2);
+	}
+
+	#[test]
+	fn orthonormal_basis_test() {
+		for normal in [
+			Vector::new(0.0, 1.0, 0.0),
+			Vector::new(0.0, 0.0, -1.0),
+			Vector::new(1.0, 2.0, 3.0).normalise(),
+		] {
+			let (t, b) = orthonormal_basis(&normal);
+			approx::assert_relative_eq!(t.magnitude(), 1.0, epsilon = 1e-9);
+			approx::assert_relative_eq!(b.magnitude(), 1.0, epsilon = 1e-9);
+			approx::assert_relative_eq!(t.dot(&normal), 0.0, epsilon = 1e-9);
+			approx::assert_relative_eq!(b.dot(&normal), 0.0, epsilon = 1e-9);
+			approx::assert_relative_eq!(t.dot(&b), 0.0, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn hemisphere_sampling_test() {
+		let normal = Vector::new(0.0, 1.0, 0.0);
+		let mut s = Sampler::new(3);
+		let n = 20000;
+		let mut mean_cos = 0.0;
+		for _ in 0..n {
+			let (u1, u2) = s.next_2d();
+			let d = cosine_sample_hemisphere(&normal, u1, u2);
+			approx::assert_relative_eq!(d.magnitude(), 1.0, epsilon = 1e-9);
+			assert!(d.dot(&normal) >= 0.0);
+			mean_cos += d.dot(&normal);
+		}
+		// E[cos] for a cosine weighted hemisphere is 2/3
+		approx::assert_relative_eq!(mean_cos / n as f64, 2.0 / 3.0, epsilon = 1e-2);
+
+		let d = uniform_sample_sphere(0.25, 0.5);
+		approx::assert_relative_eq!(d.magnitude(), 1.0, epsilon = 1e-9);
+	}
+}
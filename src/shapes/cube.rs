@@ -2,8 +2,9 @@ use std::f64;
 
 use crate::{
 	intersection::Intersection,
+	patterns::uv_mapping::{cube_face, cube_map, CubeFace},
 	primitives::{point::Point, ray::Ray, vector::Vector},
-	shapes::shape::{ConcreteShape, Shape},
+	shapes::shape::{ConcreteShape, Shape, SurfaceSample},
 };
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -58,6 +59,44 @@ impl ConcreteShape for Cube {
 		}
 	}
 
+	fn can_sample_surface(&self) -> bool {
+		true
+	}
+
+	fn local_sample_surface(&self, u1: f64, u2: f64) -> Option<SurfaceSample> {
+		// pick one of the six faces and reuse u1 to place the point on it
+		let face = ((u1 * 6.0) as usize).min(5);
+		let a = 2.0 * (u1 * 6.0 - face as f64) - 1.0;
+		let b = 2.0 * u2 - 1.0;
+		let (point, normal) = match face {
+			0 => (Point::new(1.0, a, b), Vector::new(1.0, 0.0, 0.0)),
+			1 => (Point::new(-1.0, a, b), Vector::new(-1.0, 0.0, 0.0)),
+			2 => (Point::new(a, 1.0, b), Vector::new(0.0, 1.0, 0.0)),
+			3 => (Point::new(a, -1.0, b), Vector::new(0.0, -1.0, 0.0)),
+			4 => (Point::new(a, b, 1.0), Vector::new(0.0, 0.0, 1.0)),
+			_ => (Point::new(a, b, -1.0), Vector::new(0.0, 0.0, -1.0)),
+		};
+		// six faces of area 4
+		Some(SurfaceSample { point, normal, pdf: 1.0 / 24.0 })
+	}
+
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		cube_map(&point)
+	}
+
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		let (x, y, z) =
+			(Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+		match cube_face(&point) {
+			CubeFace::Front => (x, y),
+			CubeFace::Back => (-x, y),
+			CubeFace::Left => (z, y),
+			CubeFace::Right => (-z, y),
+			CubeFace::Up => (x, -z),
+			CubeFace::Down => (x, z),
+		}
+	}
+
 	fn shape(&self) -> &Shape {
 		&self.shape
 	}
@@ -179,4 +218,14 @@ mod tests {
 		let xs = c.local_intersect(r).unwrap();
 		assert_eq!(xs.len(), 0);
 	}
+
+	#[test]
+	fn test_sample_surface() {
+		let c = Cube::default();
+		for (u1, u2) in [(0.05, 0.5), (0.2, 0.1), (0.4, 0.9), (0.6, 0.3), (0.7, 0.7), (0.95, 0.2)] {
+			let sample = c.local_sample_surface(u1, u2).unwrap();
+			approx::assert_relative_eq!(sample.pdf, 1.0 / 24.0);
+			assert_eq!(c.local_normal_at(sample.point), sample.normal);
+		}
+	}
 }
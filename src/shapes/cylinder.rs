@@ -2,6 +2,7 @@ use std::f64;
 
 use crate::{
 	intersection::Intersection,
+	patterns::uv_mapping::cylindrical_map,
 	primitives::{point::Point, ray::Ray, vector::Vector},
 	shapes::shape::{ConcreteShape, Shape},
 };
@@ -83,6 +84,14 @@ impl ConcreteShape for Cylinder {
 		Ok(xs)
 	}
 
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		cylindrical_map(&point)
+	}
+
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		(Vector::new(-point.tuple.z, 0.0, point.tuple.x), Vector::new(0.0, 1.0, 0.0))
+	}
+
 	fn shape(&self) -> &Shape {
 		&self.shape
 	}
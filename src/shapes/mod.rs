@@ -4,3 +4,4 @@ pub mod cylinder;
 pub mod plane;
 pub mod shape;
 pub mod spheres;
+pub mod triangle;
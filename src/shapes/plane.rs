@@ -2,6 +2,7 @@ use std::f64;
 
 use crate::{
 	intersection::Intersection,
+	patterns::uv_mapping::planar_map,
 	primitives::{point::Point, ray::Ray, vector::Vector},
 	shapes::shape::{ConcreteShape, Shape},
 };
@@ -27,11 +28,20 @@ impl ConcreteShape for Plane {
 		let mut x = Vec::new();
 		if ray.direction.tuple.y.abs() >= f64::EPSILON {
 			let t = -ray.origin.tuple.y / ray.direction.tuple.y;
-			x.push(Intersection { t, object: self });
+			x.push(Intersection::new(t, self));
 		}
 		Ok(x)
 	}
 
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		planar_map(&point)
+	}
+
+	#[allow(unused_variables)]
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		(Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0))
+	}
+
 	fn shape(&self) -> &Shape {
 		&self.shape
 	}
@@ -1,6 +1,10 @@
 use crate::{
 	intersection::Intersection,
-	primitives::{matrix::matrix4d::Matrix4D, point::Point, ray::Ray, vector::Vector},
+	patterns::uv_mapping::spherical_map,
+	primitives::{
+		matrix::matrix4d::Matrix4D, point::Point, ray::Ray, sampler::orthonormal_basis,
+		vector::Vector,
+	},
 	visualisation::materials::Material,
 };
 use core::fmt::Debug;
@@ -22,6 +26,50 @@ pub trait ConcreteShape {
 	}
 	fn local_normal_at(&self, point: Point) -> Vector;
 
+	// Texture coordinates of an object space point on the surface
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		spherical_map(&point)
+	}
+
+	fn uv_at(&self, point: Point) -> (f64, f64) {
+		self.local_uv_at(self.transform().inverse().unwrap() * point)
+	}
+
+	// Directions in which u and v grow at an object space point, not necessarily unit length
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		orthonormal_basis(&self.local_normal_at(point))
+	}
+
+	fn tangents_at(&self, point: Point) -> (Vector, Vector) {
+		let local_point = self.transform().inverse().unwrap() * point;
+		let (tangent, bitangent) = self.local_tangents_at(local_point);
+		(*self.transform() * tangent, *self.transform() * bitangent)
+	}
+
+	// Uniformly distributed point on the surface in world space. Used to sample emissive shapes
+	// as area lights, the pdf is with respect to the surface area.
+	fn sample_surface(&self, u1: f64, u2: f64) -> Option<SurfaceSample> {
+		let local = self.local_sample_surface(u1, u2)?;
+		let point = *self.transform() * local.point;
+		let mut normal = self.transform().inverse()?.transpose() * local.normal;
+		normal.tuple.w = 0.0;
+		// the transformation stretches a surface element by det(M) * |M^-T * n|
+		let area_scale = self.transform().det().abs() * normal.magnitude();
+		Some(SurfaceSample { point, normal: normal.normalise(), pdf: local.pdf / area_scale })
+	}
+
+	// Same as `sample_surface` but in object space, unbounded shapes can not be sampled
+	#[allow(unused_variables)]
+	fn local_sample_surface(&self, u1: f64, u2: f64) -> Option<SurfaceSample> {
+		None
+	}
+
+	// Whether `local_sample_surface` returns samples, which shapes overriding it have to tell.
+	// Emitters which can not be sampled are only seen when a ray happens to hit them.
+	fn can_sample_surface(&self) -> bool {
+		false
+	}
+
 	fn transform(&self) -> &Matrix4D {
 		&self.shape().transform
 	}
@@ -46,6 +94,14 @@ pub trait ConcreteShape {
 		&mut self.get_shape().material
 	}
 
+	fn flags(&self) -> &RenderFlags {
+		&self.shape().flags
+	}
+
+	fn get_flags(&mut self) -> &mut RenderFlags {
+		&mut self.get_shape().flags
+	}
+
 	fn set_origin(&mut self, origin: Point) {
 		self.get_shape().origin = origin
 	}
@@ -80,16 +136,64 @@ impl<'a> PartialOrd for dyn ConcreteShape + 'a {
 	}
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceSample {
+	pub point: Point,
+	pub normal: Vector,
+	pub pdf: f64,
+}
+
+// Which parts of the render an object takes part in, everything is on by default
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct RenderFlags {
+	// blocks the light on its way to other objects
+	pub cast_shadows: bool,
+	// gets darker where other objects block the light
+	pub receive_shadows: bool,
+	// seen by rays leaving the camera
+	pub camera_visible: bool,
+	// seen by reflected, refracted and other bounced rays
+	pub reflection_visible: bool,
+}
+
+impl RenderFlags {
+	// whether a ray leaving the camera, or one which has bounced off something, sees the object
+	pub fn visible(&self, camera: bool) -> bool {
+		if camera {
+			self.camera_visible
+		} else {
+			self.reflection_visible
+		}
+	}
+}
+
+impl Default for RenderFlags {
+	fn default() -> Self {
+		Self {
+			cast_shadows: true,
+			receive_shadows: true,
+			camera_visible: true,
+			reflection_visible: true,
+		}
+	}
+}
+
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct Shape {
 	pub origin: Point,
 	pub transform: Matrix4D,
 	pub material: Material,
+	pub flags: RenderFlags,
 }
 
 impl Shape {
 	pub fn new(origin: Point) -> Self {
-		Self { origin, transform: Matrix4D::identity(), material: Material::default() }
+		Self {
+			origin,
+			transform: Matrix4D::identity(),
+			material: Material::default(),
+			flags: RenderFlags::default(),
+		}
 	}
 }
 
@@ -99,6 +203,7 @@ impl Default for Shape {
 			origin: Point::new(0.0, 0.0, 0.0),
 			transform: Matrix4D::identity(),
 			material: Material::default(),
+			flags: RenderFlags::default(),
 		}
 	}
 }
@@ -130,7 +235,31 @@ mod tests {
 		let mut m = Material::default();
 		m.ambient = 1.0;
 
-		s.material = m;
+		s.material = m.clone();
 		assert_eq!(s.material, m);
+
+		// default flags
+		assert_eq!(s.flags, RenderFlags::default());
+		assert!(s.flags.visible(true) && s.flags.visible(false));
+		s.flags.camera_visible = false;
+		assert!(!s.flags.visible(true) && s.flags.visible(false));
+	}
+
+	#[test]
+	fn sample_surface_test() {
+		use crate::shapes::{plane::Plane, spheres::Sphere};
+
+		// unbounded shapes can not be sampled
+		assert!(Plane::default().sample_surface(0.5, 0.5).is_none());
+		assert!(!Plane::default().can_sample_surface());
+		assert!(Sphere::default().can_sample_surface());
+
+		// scaling the unit sphere by 2 makes its area 4 times larger
+		let mut s = Sphere::default();
+		s.set_transform(translation(1.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0));
+		let sample = s.sample_surface(0.25, 0.5).unwrap();
+		approx::assert_relative_eq!(sample.pdf, 1.0 / (16.0 * std::f64::consts::PI));
+		approx::assert_relative_eq!((sample.point - Point::new(1.0, 0.0, 0.0)).magnitude(), 2.0);
+		approx::assert_relative_eq!(sample.normal, s.normal_at(sample.point), epsilon = 1e-9);
 	}
 }
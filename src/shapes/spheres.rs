@@ -1,8 +1,10 @@
 use crate::{
 	intersection::Intersection,
-	primitives::{point::Point, ray::Ray, vector::Vector},
-	shapes::shape::{ConcreteShape, Shape},
+	patterns::uv_mapping::spherical_map,
+	primitives::{point::Point, ray::Ray, sampler::uniform_sample_sphere, vector::Vector},
+	shapes::shape::{ConcreteShape, Shape, SurfaceSample},
 };
+use std::f64::consts::PI;
 
 use approx::RelativeEq;
 
@@ -46,13 +48,34 @@ impl ConcreteShape for Sphere {
 		if discriminant >= 0.0 {
 			let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
 			let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-			is.push(Intersection { t: t1, object: self });
-			is.push(Intersection { t: t2, object: self });
+			is.push(Intersection::new(t1, self));
+			is.push(Intersection::new(t2, self));
 		}
 
 		Ok(is)
 	}
 
+	fn can_sample_surface(&self) -> bool {
+		true
+	}
+
+	fn local_sample_surface(&self, u1: f64, u2: f64) -> Option<SurfaceSample> {
+		let normal = uniform_sample_sphere(u1, u2);
+		Some(SurfaceSample { point: *self.origin() + normal, normal, pdf: 1.0 / (4.0 * PI) })
+	}
+
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		let v = point - *self.origin();
+		spherical_map(&Point::new(v.tuple.x, v.tuple.y, v.tuple.z))
+	}
+
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		let p = (point - *self.origin()).tuple;
+		let tangent = Vector::new(-p.z, 0.0, p.x);
+		let bitangent = Vector::new(-p.x * p.y, p.x * p.x + p.z * p.z, -p.y * p.z);
+		(tangent, bitangent)
+	}
+
 	fn shape(&self) -> &Shape {
 		&self.shape
 	}
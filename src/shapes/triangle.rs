@@ -0,0 +1,183 @@
+use crate::{
+	intersection::Intersection,
+	primitives::{point::Point, ray::Ray, vector::Vector},
+	shapes::shape::{ConcreteShape, Shape, SurfaceSample},
+};
+
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct Triangle {
+	shape: Shape,
+	pub p1: Point,
+	pub p2: Point,
+	pub p3: Point,
+	pub e1: Vector,
+	pub e2: Vector,
+	pub normal: Vector,
+}
+
+impl Triangle {
+	pub fn new(p1: Point, p2: Point, p3: Point) -> Self {
+		let e1 = p2 - p1;
+		let e2 = p3 - p1;
+		let normal = e2.cross(&e1).normalise();
+		Self { shape: Shape::new(p1), p1, p2, p3, e1, e2, normal }
+	}
+
+	pub fn area(&self) -> f64 {
+		self.e1.cross(&self.e2).magnitude() / 2.0
+	}
+}
+
+impl ConcreteShape for Triangle {
+	#[allow(unused_variables)]
+	fn local_normal_at(&self, point: Point) -> Vector {
+		self.normal
+	}
+
+	fn local_intersect<'i>(&'i self, ray: Ray) -> Result<Vec<Intersection<'i>>, String> {
+		// Möller–Trumbore algorithm
+		let dir_cross_e2 = ray.direction.cross(&self.e2);
+		let det = self.e1.dot(&dir_cross_e2);
+		if det.abs() < f64::EPSILON {
+			return Ok(vec![])
+		}
+
+		let f = 1.0 / det;
+		let p1_to_origin = ray.origin - self.p1;
+		let u = f * p1_to_origin.dot(&dir_cross_e2);
+		if !(0.0..=1.0).contains(&u) {
+			return Ok(vec![])
+		}
+
+		let origin_cross_e1 = p1_to_origin.cross(&self.e1);
+		let v = f * ray.direction.dot(&origin_cross_e1);
+		if v < 0.0 || u + v > 1.0 {
+			return Ok(vec![])
+		}
+
+		let t = f * self.e2.dot(&origin_cross_e1);
+		Ok(vec![Intersection::new_with_uv(t, self, u, v)])
+	}
+
+	fn can_sample_surface(&self) -> bool {
+		true
+	}
+
+	fn local_sample_surface(&self, u1: f64, u2: f64) -> Option<SurfaceSample> {
+		let s = u1.sqrt();
+		let (b1, b2) = (u2 * s, 1.0 - s);
+		Some(SurfaceSample {
+			point: self.p1 + self.e1 * b1 + self.e2 * b2,
+			normal: self.normal,
+			pdf: 1.0 / self.area(),
+		})
+	}
+
+	// barycentric weights of p2 and p3, the same ones `local_intersect` reports
+	fn local_uv_at(&self, point: Point) -> (f64, f64) {
+		let d = point - self.p1;
+		let (d00, d01, d11) = (self.e1.dot(&self.e1), self.e1.dot(&self.e2), self.e2.dot(&self.e2));
+		let (d20, d21) = (d.dot(&self.e1), d.dot(&self.e2));
+		let denom = d00 * d11 - d01 * d01;
+		((d11 * d20 - d01 * d21) / denom, (d00 * d21 - d01 * d20) / denom)
+	}
+
+	#[allow(unused_variables)]
+	fn local_tangents_at(&self, point: Point) -> (Vector, Vector) {
+		(self.e1, self.e2)
+	}
+
+	fn shape(&self) -> &Shape {
+		&self.shape
+	}
+
+	fn get_shape(&mut self) -> &mut Shape {
+		&mut self.shape
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn default_triangle() -> Triangle {
+		Triangle::new(
+			Point::new(0.0, 1.0, 0.0),
+			Point::new(-1.0, 0.0, 0.0),
+			Point::new(1.0, 0.0, 0.0),
+		)
+	}
+
+	#[test]
+	fn construction_test() {
+		let t = default_triangle();
+		assert_eq!(t.e1, Vector::new(-1.0, -1.0, 0.0));
+		assert_eq!(t.e2, Vector::new(1.0, -1.0, 0.0));
+		assert_eq!(t.normal, Vector::new(0.0, 0.0, -1.0));
+		approx::assert_relative_eq!(t.area(), 1.0);
+
+		for p in
+			[Point::new(0.0, 0.5, 0.0), Point::new(-0.5, 0.75, 0.0), Point::new(0.5, 0.25, 0.0)]
+		{
+			assert_eq!(t.local_normal_at(p), t.normal);
+		}
+	}
+
+	#[test]
+	fn test_intersections() {
+		let t = default_triangle();
+
+		// ray parallel to the triangle
+		let r = Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 0.0));
+		assert_eq!(t.local_intersect(r).unwrap().len(), 0);
+
+		// ray misses the p1-p3 edge
+		let r = Ray::new(Point::new(1.0, 1.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+		assert_eq!(t.local_intersect(r).unwrap().len(), 0);
+
+		// ray misses the p1-p2 edge
+		let r = Ray::new(Point::new(-1.0, 1.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+		assert_eq!(t.local_intersect(r).unwrap().len(), 0);
+
+		// ray misses the p2-p3 edge
+		let r = Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 0.0, 1.0));
+		assert_eq!(t.local_intersect(r).unwrap().len(), 0);
+
+		// ray strikes the triangle
+		let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = t.local_intersect(r).unwrap();
+		assert_eq!(xs.len(), 1);
+		approx::assert_relative_eq!(xs[0].t, 2.0);
+	}
+
+	#[test]
+	fn test_uv() {
+		let t = default_triangle();
+		let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = t.local_intersect(r).unwrap();
+		let (u, v) = xs[0].uv.unwrap();
+		approx::assert_relative_eq!(u, 0.45, epsilon = 1e-9);
+		approx::assert_relative_eq!(v, 0.25, epsilon = 1e-9);
+		let (pu, pv) = t.local_uv_at(Point::new(-0.2, 0.3, 0.0));
+		approx::assert_relative_eq!(pu, u, epsilon = 1e-9);
+		approx::assert_relative_eq!(pv, v, epsilon = 1e-9);
+
+		// vertices map onto the corners of the uv triangle
+		assert_eq!(t.local_uv_at(t.p1), (0.0, 0.0));
+		assert_eq!(t.local_uv_at(t.p2), (1.0, 0.0));
+		assert_eq!(t.local_uv_at(t.p3), (0.0, 1.0));
+	}
+
+	#[test]
+	fn test_sample_surface() {
+		let t = default_triangle();
+		for (u1, u2) in [(0.2, 0.2), (0.3, 0.9), (0.99, 0.5), (0.5, 0.1)] {
+			let sample = t.local_sample_surface(u1, u2).unwrap();
+			approx::assert_relative_eq!(sample.pdf, 1.0);
+			// sampled point lies on the triangle
+			let r =
+				Ray::new(sample.point + Vector::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0));
+			assert_eq!(t.local_intersect(r).unwrap().len(), 1);
+		}
+	}
+}
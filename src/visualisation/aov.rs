@@ -0,0 +1,136 @@
+use crate::{
+	intersection::hit,
+	primitives::{color::Color, ray::Ray, vector::Vector},
+	visualisation::{canvas::Canvas, world::World},
+};
+
+// Arbitrary output variables, per pixel data about the first surface seen through it which is
+// rendered next to the beauty image for compositing and debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+	// distance along the camera's viewing direction, infinite for misses
+	Depth,
+	// world space shading normal facing the camera
+	Normal,
+	// world space position
+	Position,
+	// surface colour before any lighting
+	Albedo,
+	// 1 + index of the object in `World::objects`, 0 for misses
+	ObjectId,
+	// 1 + index of the first object whose material has the same `Material::id`, or of the object
+	// itself when its material has none, 0 for misses
+	MaterialId,
+	// number of surfaces the camera ray crosses
+	HitCount,
+}
+
+impl Aov {
+	pub const ALL: [Aov; 7] = [
+		Aov::Depth,
+		Aov::Normal,
+		Aov::Position,
+		Aov::Albedo,
+		Aov::ObjectId,
+		Aov::MaterialId,
+		Aov::HitCount,
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Aov::Depth => "depth",
+			Aov::Normal => "normal",
+			Aov::Position => "position",
+			Aov::Albedo => "albedo",
+			Aov::ObjectId => "object_id",
+			Aov::MaterialId => "material_id",
+			Aov::HitCount => "hit_count",
+		}
+	}
+
+	// Material ID of every object in `World::objects`, worked out once per render
+	pub fn material_ids(world: &World) -> Vec<usize> {
+		let ids: Vec<_> = world.objects.iter().map(|o| o.material().id).collect();
+		ids.iter()
+			.enumerate()
+			.map(|(i, id)| match id {
+				Some(id) => ids.iter().position(|other| *other == Some(*id)).unwrap_or(i) + 1,
+				None => i + 1,
+			})
+			.collect()
+	}
+
+	// Values of `aovs` for the camera ray `ray`, `forward` is the direction the camera looks at and
+	// `material_ids` comes from `Aov::material_ids`
+	pub fn sample(
+		aovs: &[Aov],
+		world: &World,
+		ray: &Ray,
+		forward: &Vector,
+		material_ids: &[usize],
+	) -> Result<Vec<Color>, String> {
+		let grey = |v: f64| Color::new(v, v, v);
+		let xs = world.visible_intersections(ray, true)?;
+		let intersection = match hit(&xs) {
+			Some(intersection) => intersection,
+			None => {
+				let miss = |aov: &Aov| match aov {
+					Aov::Depth => grey(f64::INFINITY),
+					_ => grey(0.0),
+				};
+				return Ok(aovs.iter().map(miss).collect())
+			},
+		};
+		let comps = ray.prepare_computations(intersection, Some(&xs));
+		let index = world.objects.iter().position(|o| std::ptr::addr_eq(&**o, comps.object));
+
+		Ok(aovs
+			.iter()
+			.map(|aov| match aov {
+				Aov::Depth => grey(comps.t * ray.direction.dot(forward)),
+				Aov::Normal => {
+					let n = comps.normal.tuple;
+					Color::new(n.x, n.y, n.z)
+				},
+				Aov::Position => {
+					let p = comps.point.tuple;
+					Color::new(p.x, p.y, p.z)
+				},
+				Aov::Albedo => comps.object.material().color_at(comps.object, &comps.point),
+				Aov::ObjectId => grey(index.map_or(0.0, |i| i as f64 + 1.0)),
+				Aov::MaterialId =>
+					grey(index.and_then(|i| material_ids.get(i)).map_or(0.0, |id| *id as f64)),
+				Aov::HitCount => grey(xs.iter().filter(|i| i.t >= 0.0).count() as f64),
+			})
+			.collect())
+	}
+}
+
+// Beauty image of a render together with the requested AOV buffers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOutput {
+	pub beauty: Canvas,
+	pub buffers: Vec<(Aov, Canvas)>,
+}
+
+impl RenderOutput {
+	pub fn buffer(&self, aov: Aov) -> Option<&Canvas> {
+		self.buffers.iter().find(|(a, _)| *a == aov).map(|(_, canvas)| canvas)
+	}
+
+	pub fn named(&self, name: &str) -> Option<&Canvas> {
+		match name {
+			"beauty" => Some(&self.beauty),
+			_ => self.buffers.iter().find(|(a, _)| a.name() == name).map(|(_, canvas)| canvas),
+		}
+	}
+
+	// Writes every buffer as `<prefix>_<name>.pfm`, beauty included
+	pub fn write(&self, prefix: &str) -> Result<(), String> {
+		self.beauty.to_pfm(&format!("{}_beauty.pfm", prefix))?;
+		for (aov, canvas) in &self.buffers {
+			canvas.to_pfm(&format!("{}_{}.pfm", prefix, aov.name()))?;
+		}
+		Ok(())
+	}
+}
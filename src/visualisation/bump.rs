@@ -0,0 +1,213 @@
+use crate::{
+	patterns::{image_pattern::ImagePattern, noise::Noise, uv_mapping::UvMapping},
+	primitives::{point::Point, sampler::orthonormal_basis, vector::Vector},
+	shapes::shape::ConcreteShape,
+	visualisation::canvas::Canvas,
+};
+
+// step used to differentiate procedural height fields
+const GRADIENT_STEP: f64 = 1e-4;
+
+// Perturbs the shading normal of a material without changing the geometry
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Bump {
+	// height field `amplitude * noise(frequency * p)` over the object space point
+	Noise { noise: Noise, amplitude: f64, frequency: f64 },
+	// concentric waves `amplitude * sin(frequency * |p|)` around the object space origin
+	Ripples { amplitude: f64, frequency: f64 },
+	// tangent space normals stored as colours, sampled at the uv coordinates of the hit. Red and
+	// green follow the u and v directions of the shape, blue points along the normal.
+	NormalMap { image: ImagePattern, strength: f64 },
+}
+
+impl Bump {
+	pub fn new_noise(amplitude: f64, frequency: f64) -> Self {
+		Self::Noise { noise: Noise::default(), amplitude, frequency }
+	}
+
+	pub fn new_ripples(amplitude: f64, frequency: f64) -> Self {
+		Self::Ripples { amplitude, frequency }
+	}
+
+	pub fn new_normal_map(canvas: Canvas) -> Self {
+		Self::NormalMap { image: ImagePattern::new(canvas, UvMapping::Shape), strength: 1.0 }
+	}
+
+	// Height of the procedural bumps at an object space point
+	pub fn height(&self, point: &Point) -> f64 {
+		match self {
+			Self::Noise { noise, amplitude, frequency } => {
+				let p = point.tuple;
+				amplitude *
+					noise.noise(&Point::new(p.x * frequency, p.y * frequency, p.z * frequency))
+			},
+			Self::Ripples { amplitude, frequency } => {
+				let p = point.tuple;
+				amplitude * (frequency * (p.x * p.x + p.y * p.y + p.z * p.z).sqrt()).sin()
+			},
+			Self::NormalMap { .. } => 0.0,
+		}
+	}
+
+	// Shading normal at a world space `point` with the unperturbed `normal` and texture
+	// coordinates `uv`
+	pub fn perturb(
+		&self,
+		object: &dyn ConcreteShape,
+		point: &Point,
+		normal: &Vector,
+		uv: (f64, f64),
+	) -> Vector {
+		match self {
+			Self::NormalMap { image, strength } => {
+				let (tangent, bitangent) = tangent_frame(object, point, normal);
+				let c = image.uv_pattern_at(uv.0, uv.1);
+				let x = (2.0 * c.red - 1.0) * strength;
+				let y = (2.0 * c.green - 1.0) * strength;
+				let z = 2.0 * c.blue - 1.0;
+				(tangent * x + bitangent * y + *normal * z).normalise()
+			},
+			_ => {
+				let inverse = object.transform().inverse().expect("Cannot invert object transform");
+				let p = inverse * *point;
+				let step = |dx: f64, dy: f64, dz: f64| {
+					let offset = Vector::new(dx, dy, dz);
+					(self.height(&(p + offset)) - self.height(&(p - offset))) /
+						(2.0 * GRADIENT_STEP)
+				};
+				let local_gradient = Vector::new(
+					step(GRADIENT_STEP, 0.0, 0.0),
+					step(0.0, GRADIENT_STEP, 0.0),
+					step(0.0, 0.0, GRADIENT_STEP),
+				);
+				let mut gradient = inverse.transpose() * local_gradient;
+				gradient.tuple.w = 0.0;
+				// only the part of the slope along the surface tilts the normal
+				let along_surface = gradient - *normal * gradient.dot(normal);
+				(*normal - along_surface).normalise()
+			},
+		}
+	}
+}
+
+// Unit tangent and bitangent perpendicular to `normal`, following the uv directions of the shape
+fn tangent_frame(object: &dyn ConcreteShape, point: &Point, normal: &Vector) -> (Vector, Vector) {
+	let (tangent, bitangent) = object.tangents_at(*point);
+	let tangent = tangent - *normal * tangent.dot(normal);
+	if tangent.magnitude() < 1e-9 {
+		return orthonormal_basis(normal)
+	}
+	let tangent = tangent.normalise();
+	let bitangent = bitangent - *normal * bitangent.dot(normal) - tangent * bitangent.dot(&tangent);
+	if bitangent.magnitude() < 1e-9 {
+		return (tangent, normal.cross(&tangent))
+	}
+	(tangent, bitangent.normalise())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		primitives::{color::Color, transformations::*},
+		shapes::{plane::Plane, spheres::Sphere},
+	};
+
+	#[test]
+	fn ripples_test() {
+		let bump = Bump::new_ripples(0.1, 2.0);
+		let up = Vector::new(0.0, 1.0, 0.0);
+
+		// height is flat at the crest, sloped elsewhere
+		let plane = Plane::default();
+		let crest = std::f64::consts::PI / 4.0;
+		let n = bump.perturb(&plane, &Point::new(crest, 0.0, 0.0), &up, (0.0, 0.0));
+		approx::assert_relative_eq!(n, up, epsilon = 1e-6);
+
+		let n = bump.perturb(&plane, &Point::new(1.0, 0.0, 0.0), &up, (0.0, 0.0));
+		let slope = 0.1 * 2.0 * 2.0_f64.cos();
+		approx::assert_relative_eq!(n, Vector::new(-slope, 1.0, 0.0).normalise(), epsilon = 1e-6);
+
+		// bumps follow the object transform
+		let mut plane = Plane::default();
+		plane.set_transform(scaling(2.0, 1.0, 2.0));
+		let n = bump.perturb(&plane, &Point::new(2.0, 0.0, 0.0), &up, (0.0, 0.0));
+		approx::assert_relative_eq!(
+			n,
+			Vector::new(-slope / 2.0, 1.0, 0.0).normalise(),
+			epsilon = 1e-6
+		);
+	}
+
+	#[test]
+	fn noise_test() {
+		let bump = Bump::new_noise(0.5, 4.0);
+		let sphere = Sphere::default();
+		let mut tilted = 0;
+		for (x, y, z) in [(0.0, 0.0, -1.0), (0.6, 0.0, 0.8), (0.0, 1.0, 0.0), (-0.48, 0.6, -0.64)] {
+			let p = Point::new(x, y, z);
+			let normal = sphere.normal_at(p);
+			let n = bump.perturb(&sphere, &p, &normal, (0.0, 0.0));
+			approx::assert_relative_eq!(n.magnitude(), 1.0, epsilon = 1e-9);
+			assert!(n.dot(&normal) > 0.0);
+			if n.dot(&normal) < 0.9999 {
+				tilted += 1;
+			}
+		}
+		assert!(tilted > 0);
+	}
+
+	#[test]
+	fn normal_map_test() {
+		let mut canvas = Canvas::new(2, 1);
+		// flat on the left, leaning towards +u on the right
+		canvas.write_pixel(0, 0, Color::new(0.5, 0.5, 1.0));
+		canvas.write_pixel(1, 0, Color::new(1.0, 0.5, 0.5));
+		let mut bump = Bump::new_normal_map(canvas);
+		if let Bump::NormalMap { image, .. } = &mut bump {
+			image.filter = crate::patterns::image_pattern::TextureFilter::Nearest;
+		}
+
+		let plane = Plane::default();
+		let up = Vector::new(0.0, 1.0, 0.0);
+		let p = Point::new(0.0, 0.0, 0.0);
+		approx::assert_relative_eq!(bump.perturb(&plane, &p, &up, (0.25, 0.5)), up);
+		approx::assert_relative_eq!(
+			bump.perturb(&plane, &p, &up, (0.75, 0.5)),
+			Vector::new(1.0, 0.0, 0.0)
+		);
+
+		// tangent space follows the uv directions of a sphere, u grows towards +x in front
+		let sphere = Sphere::default();
+		let front = Point::new(0.0, 0.0, -1.0);
+		let normal = sphere.normal_at(front);
+		approx::assert_relative_eq!(
+			bump.perturb(&sphere, &front, &normal, (0.75, 0.5)),
+			Vector::new(1.0, 0.0, 0.0),
+			epsilon = 1e-9
+		);
+	}
+
+	#[test]
+	fn tangents_test() {
+		let sphere = Sphere::default();
+		let front = Point::new(0.0, 0.0, -1.0);
+		let (t, b) = sphere.tangents_at(front);
+		approx::assert_relative_eq!(t, Vector::new(1.0, 0.0, 0.0));
+		approx::assert_relative_eq!(b, Vector::new(0.0, 1.0, 0.0));
+
+		// tangents of transformed shapes are transformed as directions
+		let mut plane = Plane::default();
+		plane.set_transform(rotation_z(std::f64::consts::PI / 2.0));
+		let (t, b) = plane.tangents_at(Point::new(0.0, 1.0, 0.0));
+		approx::assert_relative_eq!(t, Vector::new(0.0, 1.0, 0.0), epsilon = 1e-9);
+		approx::assert_relative_eq!(b, Vector::new(0.0, 0.0, 1.0), epsilon = 1e-9);
+
+		// frames stay orthonormal even for degenerate tangents at the pole
+		let top = Point::new(0.0, 1.0, 0.0);
+		let (t, b) = tangent_frame(&sphere, &top, &Vector::new(0.0, 1.0, 0.0));
+		approx::assert_relative_eq!(t.dot(&b), 0.0, epsilon = 1e-9);
+		approx::assert_relative_eq!(t.magnitude(), 1.0, epsilon = 1e-9);
+		approx::assert_relative_eq!(b.magnitude(), 1.0, epsilon = 1e-9);
+	}
+}
@@ -1,8 +1,25 @@
 use crate::{
-	primitives::{matrix::matrix4d::Matrix4D, point::Point, ray::Ray},
-	visualisation::{canvas::Canvas, world::World},
+	primitives::{
+		color::Color, matrix::matrix4d::Matrix4D, point::Point, ray::Ray, sampler::Sampler,
+		vector::Vector,
+	},
+	visualisation::{
+		aov::{Aov, RenderOutput},
+		canvas::Canvas,
+		integrator::Integrator,
+		progress::{CancellationToken, Progress, RenderReport},
+		progressive::{Accumulator, PassSnapshot, Progressive, ProgressiveRender, StopReason},
+		spectrum::{film_response, sample_wavelength},
+		world::World,
+	},
 };
-use std::f64;
+use std::{
+	f64,
+	time::{Duration, Instant},
+};
+
+// wavelengths traced per pixel by spectral Whitted renders
+const SPECTRAL_SAMPLES: usize = 32;
 
 #[derive(Debug)]
 pub struct Camera {
@@ -13,6 +30,9 @@ pub struct Camera {
 	pub pixel_size: f64,
 	pub half_width: f64,
 	pub half_height: f64,
+	pub integrator: Integrator,
+	// rays carry single wavelengths and pixels are built from CIE XYZ instead of RGB channels
+	pub spectral: bool,
 }
 
 impl Camera {
@@ -38,6 +58,8 @@ impl Camera {
 			pixel_size,
 			half_width,
 			half_height,
+			integrator: Integrator::default(),
+			spectral: false,
 		}
 	}
 
@@ -60,26 +82,178 @@ impl Camera {
 	}
 
 	pub fn render(&self, world: &World) -> Result<Canvas, String> {
-		let mut image = Canvas::new(self.hsize as usize, self.vsize as usize);
-
-		for y in 0..self.vsize as usize {
-			for x in 0..self.hsize as usize {
-				let r = self.ray_for_pixel(x as f64, y as f64);
-				let color = world.color_at(&r, None);
-				match color {
-					Ok(color_value) => image.write_pixel(x, y, color_value),
-					Err(err) => return Err(err),
+		Ok(self.render_with_aovs(world, &[])?.beauty)
+	}
+
+	// Renders the beauty image and the `aovs` buffers in the same pass. The AOVs describe the
+	// surface seen through the centre of each pixel.
+	pub fn render_with_aovs(&self, world: &World, aovs: &[Aov]) -> Result<RenderOutput, String> {
+		Ok(self.render_rows(world, aovs, &mut |_| {}, None)?.0)
+	}
+
+	// Renders row by row, reporting to `on_progress` after each one. Once `cancel` is triggered
+	// the render stops before the next row and returns what it has so far.
+	pub fn render_with_progress<F: FnMut(&Progress)>(
+		&self,
+		world: &World,
+		mut on_progress: F,
+		cancel: &CancellationToken,
+	) -> Result<RenderReport, String> {
+		let (output, progress) = self.render_rows(world, &[], &mut on_progress, Some(cancel))?;
+		Ok(RenderReport {
+			image: output.beauty,
+			cancelled: progress.rows_done < progress.rows,
+			progress,
+		})
+	}
+
+	fn render_rows(
+		&self,
+		world: &World,
+		aovs: &[Aov],
+		on_progress: &mut dyn FnMut(&Progress),
+		cancel: Option<&CancellationToken>,
+	) -> Result<(RenderOutput, Progress), String> {
+		let (width, height) = (self.hsize as usize, self.vsize as usize);
+		let mut image = Canvas::new(width, height);
+		let mut buffers: Vec<_> =
+			aovs.iter().map(|aov| (*aov, Canvas::new(width, height))).collect();
+		let forward = (self.transform.inverse().expect("Cannot inverse camera transform") *
+			Vector::new(0.0, 0.0, -1.0))
+		.normalise();
+		let material_ids = Aov::material_ids(world);
+		let start = Instant::now();
+		let mut progress = Progress::new(0, height, 0, Duration::ZERO);
+
+		for y in 0..height {
+			if cancel.is_some_and(|token| token.is_cancelled()) {
+				break
+			}
+			for x in 0..width {
+				let color = self.render_pixel(world, x, y, 0)?;
+				image.write_pixel(x, y, color);
+				if !aovs.is_empty() {
+					let r = self.ray_for_pixel(x as f64, y as f64);
+					let values = Aov::sample(aovs, world, &r, &forward, &material_ids)?;
+					for ((_, buffer), value) in buffers.iter_mut().zip(values) {
+						buffer.write_pixel(x, y, value);
+					}
+				}
+			}
+			let rays = (y + 1) * width * self.rays_per_pixel();
+			progress = Progress::new(y + 1, height, rays, start.elapsed());
+			on_progress(&progress);
+		}
+		Ok((RenderOutput { beauty: image, buffers }, progress))
+	}
+
+	// camera rays traced for every pixel of a pass
+	fn rays_per_pixel(&self) -> usize {
+		match &self.integrator {
+			Integrator::Whitted if self.spectral => SPECTRAL_SAMPLES,
+			Integrator::Whitted | Integrator::AmbientOcclusion(_) => 1,
+			Integrator::PathTracer(tracer) => tracer.samples,
+		}
+	}
+
+	// Renders pass after pass into a running average until `settings` says to stop, handing every
+	// intermediate image to `on_pass`. The first pass is the same as `render`.
+	pub fn render_progressive<F: FnMut(&PassSnapshot)>(
+		&self,
+		world: &World,
+		settings: &Progressive,
+		mut on_pass: F,
+	) -> Result<ProgressiveRender, String> {
+		let (width, height) = (self.hsize as usize, self.vsize as usize);
+		let start = Instant::now();
+		let mut accumulator = Accumulator::new(width, height);
+		let stop = loop {
+			let pass = accumulator.passes() as u64;
+			let mut image = Canvas::new(width, height);
+			for y in 0..height {
+				for x in 0..width {
+					image.write_pixel(x, y, self.render_pixel(world, x, y, pass)?);
 				}
 			}
+			accumulator.add(&image)?;
+
+			let noise = accumulator.noise();
+			on_pass(&PassSnapshot {
+				pass: accumulator.passes(),
+				image: accumulator.image(),
+				noise,
+				elapsed: start.elapsed(),
+			});
+			if settings.target_noise.is_some_and(|target| noise <= target) {
+				break StopReason::Converged
+			}
+			if settings.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+				break StopReason::TimeBudget
+			}
+			if accumulator.passes() >= settings.max_passes {
+				break StopReason::MaxPasses
+			}
+		};
+		Ok(ProgressiveRender {
+			variance: accumulator.variance(),
+			passes: accumulator.passes(),
+			noise: accumulator.noise(),
+			image: accumulator.image().clone(),
+			stop,
+		})
+	}
+
+	// Colour of a pixel, stochastic integrators draw different samples on every `pass`
+	fn render_pixel(&self, world: &World, x: usize, y: usize, pass: u64) -> Result<Color, String> {
+		let pixels = (self.hsize as usize * self.vsize as usize) as u64;
+		let index = (y * self.hsize as usize + x) as u64 + pass.wrapping_mul(pixels);
+		match &self.integrator {
+			Integrator::Whitted if self.spectral => {
+				let mut color = Color::new(0.0, 0.0, 0.0);
+				for i in 0..SPECTRAL_SAMPLES {
+					let wavelength = sample_wavelength(i, SPECTRAL_SAMPLES, 0.5);
+					let r =
+						self.ray_for_pixel(x as f64, y as f64).with_wavelength(Some(wavelength));
+					color = color + film_response(world.color_at(&r, None)?, wavelength);
+				}
+				Ok(color * (1.0 / SPECTRAL_SAMPLES as f64))
+			},
+			Integrator::Whitted => world.color_at(&self.ray_for_pixel(x as f64, y as f64), None),
+			Integrator::AmbientOcclusion(ao) => {
+				let mut sampler = Sampler::new(ao.seed.wrapping_add(index));
+				ao.value(world, &self.ray_for_pixel(x as f64, y as f64), &mut sampler)
+			},
+			Integrator::PathTracer(tracer) => {
+				let mut sampler = Sampler::new(tracer.seed.wrapping_add(index));
+				let mut color = Color::new(0.0, 0.0, 0.0);
+				for i in 0..tracer.samples {
+					// jitter the ray inside the pixel footprint
+					let (dx, dy) = sampler.next_2d();
+					let r = self.ray_for_pixel(x as f64 + dx - 0.5, y as f64 + dy - 0.5);
+					color = color +
+						if self.spectral {
+							// one wavelength per sample, stratified over the pixel's samples
+							let wavelength =
+								sample_wavelength(i, tracer.samples, sampler.next_f64());
+							let r = r.with_wavelength(Some(wavelength));
+							film_response(tracer.radiance(world, &r, &mut sampler)?, wavelength)
+						} else {
+							tracer.radiance(world, &r, &mut sampler)?
+						};
+				}
+				Ok(color * (1.0 / tracer.samples.max(1) as f64))
+			},
 		}
-		Ok(image)
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::primitives::{color::Color, point::Point, transformations::*, vector::Vector};
+	use crate::{
+		primitives::{point::Point, transformations::*, vector::Vector},
+		visualisation::integrator::PathTracer,
+	};
 	use std::f64;
 
 	#[test]
@@ -133,4 +307,259 @@ mod tests {
 			epsilon = 1e-5
 		);
 	}
+
+	#[test]
+	fn path_traced_render_test() {
+		let w = World::default();
+		let mut c = Camera::new(11.0, 11.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+		c.integrator = Integrator::PathTracer(PathTracer::new(4, 5));
+
+		let image = c.render(&w).unwrap();
+		// corners miss the spheres, the centre is lit
+		assert_eq!(image.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+		assert!(image.pixel_at(5, 5).green > 0.0);
+
+		// renders are reproducible for a fixed seed
+		let again = c.render(&w).unwrap();
+		assert_eq!(image.pixels, again.pixels);
+	}
+
+	#[test]
+	fn ambient_occlusion_render_test() {
+		use crate::visualisation::integrator::AmbientOcclusion;
+
+		let w = World::default();
+		let mut c = Camera::new(11.0, 11.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+		c.integrator = Integrator::AmbientOcclusion(AmbientOcclusion::new(16, 1.0));
+
+		// the outer sphere is convex and never occludes itself, misses are white
+		let image = c.render(&w).unwrap();
+		assert_eq!(image.pixel_at(0, 0), Color::new(1.0, 1.0, 1.0));
+		assert_eq!(image.pixel_at(5, 5), Color::new(1.0, 1.0, 1.0));
+	}
+
+	#[test]
+	fn aov_render_test() {
+		let mut w = World::default();
+		let mut c = Camera::new(11.0, 11.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+		let output = c.render_with_aovs(&w, &Aov::ALL).unwrap();
+		assert_eq!(output.beauty, c.render(&w).unwrap());
+		assert_eq!(output.buffers.len(), Aov::ALL.len());
+		let at = |aov, x, y| output.buffer(aov).unwrap().pixel_at(x, y);
+
+		// the centre pixel looks straight at the front of the outer sphere
+		approx::assert_relative_eq!(at(Aov::Depth, 5, 5).red, 4.0, epsilon = 1e-9);
+		approx::assert_relative_eq!(
+			at(Aov::Normal, 5, 5),
+			Color::new(0.0, 0.0, -1.0),
+			epsilon = 1e-9
+		);
+		approx::assert_relative_eq!(
+			at(Aov::Position, 5, 5),
+			Color::new(0.0, 0.0, -1.0),
+			epsilon = 1e-9
+		);
+		assert_eq!(at(Aov::Albedo, 5, 5), Color::new(0.8, 1.0, 0.6));
+		assert_eq!(at(Aov::ObjectId, 5, 5), Color::new(1.0, 1.0, 1.0));
+		assert_eq!(at(Aov::MaterialId, 5, 5), Color::new(1.0, 1.0, 1.0));
+		// both spheres are crossed twice
+		assert_eq!(at(Aov::HitCount, 5, 5), Color::new(4.0, 4.0, 4.0));
+
+		// depth is measured along the viewing direction, not along the ray
+		let depth = at(Aov::Depth, 4, 5).red;
+		approx::assert_relative_eq!(depth, at(Aov::Position, 4, 5).blue + 5.0, epsilon = 1e-9);
+		assert!(depth > 4.0);
+
+		// misses
+		assert!(at(Aov::Depth, 0, 0).red.is_infinite());
+		assert_eq!(at(Aov::ObjectId, 0, 0), Color::new(0.0, 0.0, 0.0));
+		assert_eq!(at(Aov::HitCount, 0, 0), Color::new(0.0, 0.0, 0.0));
+		assert_eq!(output.named("hit_count"), output.buffer(Aov::HitCount));
+		assert_eq!(output.named("beauty"), Some(&output.beauty));
+
+		// hiding the outer sphere shows the inner one, which has a material of its own
+		w.objects[0].get_flags().camera_visible = false;
+		let output = c.render_with_aovs(&w, &[Aov::ObjectId, Aov::MaterialId]).unwrap();
+		assert_eq!(output.buffer(Aov::ObjectId).unwrap().pixel_at(5, 5).red, 2.0);
+		assert_eq!(output.buffer(Aov::MaterialId).unwrap().pixel_at(5, 5).red, 2.0);
+		assert!(output.buffer(Aov::Depth).is_none());
+
+		output.write("test_aov").unwrap();
+		let read = Canvas::from_pfm("test_aov_object_id.pfm").unwrap();
+		for name in ["beauty", "object_id", "material_id"] {
+			std::fs::remove_file(format!("test_aov_{}.pfm", name)).unwrap();
+		}
+		assert_eq!(&read, output.buffer(Aov::ObjectId).unwrap());
+
+		// equal parameters alone do not merge materials, a shared id does
+		let outer = w.objects[0].material().clone();
+		w.objects[1].set_material(outer);
+		let material_id = |w: &World| {
+			let output = c.render_with_aovs(w, &[Aov::MaterialId]).unwrap();
+			output.buffer(Aov::MaterialId).unwrap().pixel_at(5, 5).red
+		};
+		assert_eq!(material_id(&w), 2.0);
+		w.objects[0].get_material().id = Some(7);
+		w.objects[1].get_material().id = Some(7);
+		assert_eq!(material_id(&w), 1.0);
+		assert_eq!(Aov::material_ids(&w), vec![1, 1]);
+	}
+
+	#[test]
+	fn progressive_render_test() {
+		use std::time::Duration;
+
+		let w = World::default();
+		let mut c = Camera::new(8.0, 8.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+		c.integrator = Integrator::PathTracer(PathTracer::new(1, 3));
+
+		// every pass is reported and the noise goes down as they add up
+		let mut noise = vec![];
+		let mut first = None;
+		let result = c
+			.render_progressive(&w, &Progressive::new(16), |snapshot| {
+				assert_eq!(snapshot.pass, noise.len() + 1);
+				first.get_or_insert_with(|| snapshot.image.clone());
+				noise.push(snapshot.noise);
+			})
+			.unwrap();
+		assert_eq!(result.stop, StopReason::MaxPasses);
+		assert_eq!(result.passes, 16);
+		assert_eq!(noise.len(), 16);
+		assert!(noise[0].is_infinite());
+		assert!(noise[15] < noise[2], "{:?}", noise);
+		assert_eq!(result.noise, noise[15]);
+		assert_eq!(first.unwrap(), c.render(&w).unwrap());
+		// the background never varies, the spheres do
+		assert_eq!(result.variance.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+		assert!(result.variance.pixel_at(4, 4).green > 0.0);
+
+		// stopping on the noise level or the time budget
+		let settings = Progressive { target_noise: Some(noise[7]), ..Progressive::new(100) };
+		let result = c.render_progressive(&w, &settings, |_| {}).unwrap();
+		assert_eq!(result.stop, StopReason::Converged);
+		assert_eq!(result.passes, 8);
+		let settings = Progressive { time_budget: Some(Duration::ZERO), ..Progressive::new(100) };
+		let result = c.render_progressive(&w, &settings, |_| {}).unwrap();
+		assert_eq!((result.stop, result.passes), (StopReason::TimeBudget, 1));
+
+		// deterministic integrators converge as soon as the noise can be measured
+		c.integrator = Integrator::Whitted;
+		let settings = Progressive { target_noise: Some(0.0), ..Progressive::new(100) };
+		let result = c.render_progressive(&w, &settings, |_| {}).unwrap();
+		assert_eq!((result.stop, result.passes, result.noise), (StopReason::Converged, 2, 0.0));
+		assert_eq!(result.image, c.render(&w).unwrap());
+	}
+
+	#[test]
+	fn render_progress_test() {
+		let w = World::default();
+		let mut c = Camera::new(11.0, 11.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+		let full = c.render(&w).unwrap();
+
+		// every row is reported and the result matches a plain render
+		let mut reports = vec![];
+		let token = CancellationToken::new();
+		let report = c.render_with_progress(&w, |p| reports.push(*p), &token).unwrap();
+		assert!(!report.cancelled);
+		assert_eq!(report.image, full);
+		assert_eq!(reports.len(), 11);
+		assert!(reports.iter().enumerate().all(|(i, p)| p.rows_done == i + 1 && p.rows == 11));
+		assert_eq!(report.progress, reports[10]);
+		assert_eq!(report.progress.fraction(), 1.0);
+		assert_eq!(report.progress.eta, Some(Duration::ZERO));
+
+		// cancelling halfway keeps the finished rows and leaves the rest black
+		let token = CancellationToken::new();
+		let report = c
+			.render_with_progress(
+				&w,
+				|p| {
+					if p.rows_done == 3 {
+						token.cancel();
+					}
+				},
+				&token,
+			)
+			.unwrap();
+		assert!(report.cancelled);
+		assert_eq!(report.progress.rows_done, 3);
+		assert_eq!(report.image.pixels[..33], full.pixels[..33]);
+		assert_eq!(report.image.pixel_at(5, 5), Color::new(0.0, 0.0, 0.0));
+
+		// a token cancelled up front stops the render before the first row
+		let report = c.render_with_progress(&w, |_| panic!("no rows expected"), &token).unwrap();
+		assert!(report.cancelled);
+		assert_eq!(report.progress.rows_done, 0);
+		assert_eq!(report.image, Canvas::new(11, 11));
+	}
+
+	#[test]
+	fn spectral_render_test() {
+		use crate::visualisation::{materials::Material, spectrum::Spectrum};
+
+		let mut w = World::default();
+		let mut c = Camera::new(11.0, 11.0, f64::consts::PI / 2.0);
+		c.transform = view_transform(
+			Point::new(0.0, 0.0, -5.0),
+			Point::new(0.0, 0.0, 0.0),
+			Vector::new(0.0, 1.0, 0.0),
+		);
+
+		// RGB scenes look the same through the spectral pipeline
+		let rgb = c.render(&w).unwrap();
+		c.spectral = true;
+		let spectral = c.render(&w).unwrap();
+		approx::assert_relative_eq!(spectral.pixel_at(5, 5), rgb.pixel_at(5, 5), epsilon = 0.05);
+		assert_eq!(spectral.pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+
+		// a grey spectrum behaves like its RGB colour
+		w.objects[0].set_material(Material {
+			ambient: 0.1,
+			diffuse: 0.7,
+			specular: 0.2,
+			..Material::new_spectral(Spectrum::Constant(0.5))
+		});
+		let grey = c.render(&w).unwrap().pixel_at(5, 5);
+		c.spectral = false;
+		approx::assert_relative_eq!(grey, c.render(&w).unwrap().pixel_at(5, 5), epsilon = 0.01);
+
+		// a narrow band around 500nm reflects green
+		c.spectral = true;
+		let band = Spectrum::new_sampled(490.0, 510.0, vec![0.0, 1.0, 0.0]);
+		w.objects[0].set_material(Material::new_spectral(band));
+		let narrow = c.render(&w).unwrap().pixel_at(5, 5);
+		assert!(narrow.green > narrow.red, "{:?}", narrow);
+
+		// the path tracer uses the same pipeline
+		c.integrator = Integrator::PathTracer(PathTracer::new(16, 3));
+		let traced = c.render(&w).unwrap();
+		assert!(traced.pixel_at(5, 5).green > 0.0);
+		assert_eq!(traced.pixels, c.render(&w).unwrap().pixels);
+	}
 }
@@ -3,6 +3,7 @@ use std::{fs::File, io::Write};
 
 const MAX_PPM_LINE_WIDTH: usize = 70;
 
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct Canvas {
 	pub pixels: Vec<Color>,
 	pub width: usize,
@@ -25,6 +26,177 @@ impl Canvas {
 		self.pixels[self.width * y + x]
 	}
 
+	pub fn from_ppm(path: &str) -> Result<Self, String> {
+		let data =
+			std::fs::read(path).map_err(|e| format!("Couldn't read {}. Error msg: {}", path, e))?;
+		Self::from_ppm_bytes(&data)
+	}
+
+	// Parses both plain (P3) and binary (P6) PPM images, colour values are scaled into [0, 1]
+	pub fn from_ppm_bytes(data: &[u8]) -> Result<Self, String> {
+		let mut pos = 0;
+		let mut header = Vec::with_capacity(4);
+		while header.len() < 4 {
+			header.push(next_ppm_token(data, &mut pos).ok_or("Incomplete PPM header")?);
+		}
+		let magic = header[0].clone();
+		let parse = |token: &str| -> Result<usize, String> {
+			token.parse::<usize>().map_err(|_| format!("Invalid PPM value: {}", token))
+		};
+		let (width, height, max_value) =
+			(parse(&header[1])?, parse(&header[2])?, parse(&header[3])?);
+		if max_value == 0 || max_value > 65535 {
+			return Err(format!("Invalid PPM maximum colour value: {}", max_value))
+		}
+		if width == 0 || height == 0 {
+			return Err(format!("Empty PPM image: {}x{}", width, height))
+		}
+
+		let count = width
+			.checked_mul(height)
+			.and_then(|n| n.checked_mul(3))
+			.ok_or(format!("PPM image too large: {}x{}", width, height))?;
+		let values: Vec<usize> = match magic.as_str() {
+			"P3" => (0..count)
+				.map(|_| next_ppm_token(data, &mut pos).ok_or("Missing PPM pixel data".to_string()))
+				.map(|token| token.and_then(|t| parse(&t)))
+				.collect::<Result<_, _>>()?,
+			"P6" => {
+				// a single whitespace character separates the header from the raster
+				let start = pos + 1;
+				let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+				let raster = count
+					.checked_mul(bytes_per_value)
+					.and_then(|len| data.get(start..)?.get(..len))
+					.ok_or("Missing PPM pixel data")?;
+				raster
+					.chunks(bytes_per_value)
+					.map(|c| c.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+					.collect()
+			},
+			_ => return Err(format!("Unsupported PPM format: {}", magic)),
+		};
+
+		let scale = max_value as f64;
+		let pixels = values
+			.chunks(3)
+			.map(|c| Color::new(c[0] as f64 / scale, c[1] as f64 / scale, c[2] as f64 / scale))
+			.collect();
+		Ok(Canvas { pixels, width, height })
+	}
+
+	pub fn from_hdr(path: &str) -> Result<Self, String> {
+		let data =
+			std::fs::read(path).map_err(|e| format!("Couldn't read {}. Error msg: {}", path, e))?;
+		Self::from_hdr_bytes(&data)
+	}
+
+	// Parses Radiance RGBE images, both flat and run length encoded scanlines are supported
+	pub fn from_hdr_bytes(data: &[u8]) -> Result<Self, String> {
+		let mut pos = 0;
+		let next_line = |pos: &mut usize| -> Result<String, String> {
+			let start = *pos;
+			while *pos < data.len() && data[*pos] != b'\n' {
+				*pos += 1;
+			}
+			if *pos >= data.len() {
+				return Err("Incomplete HDR header".to_string())
+			}
+			*pos += 1;
+			Ok(String::from_utf8_lossy(&data[start..*pos - 1]).into_owned())
+		};
+
+		if !next_line(&mut pos)?.starts_with("#?") {
+			return Err("Missing HDR signature".to_string())
+		}
+		loop {
+			let line = next_line(&mut pos)?;
+			if line.is_empty() {
+				break
+			}
+			if let Some(format) = line.strip_prefix("FORMAT=") {
+				if format != "32-bit_rle_rgbe" {
+					return Err(format!("Unsupported HDR format: {}", format))
+				}
+			}
+		}
+		let resolution = next_line(&mut pos)?;
+		let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+			["-Y", h, "+X", w] => (
+				h.parse::<usize>().map_err(|_| format!("Invalid HDR height: {}", h))?,
+				w.parse::<usize>().map_err(|_| format!("Invalid HDR width: {}", w))?,
+			),
+			_ => return Err(format!("Unsupported HDR orientation: {}", resolution)),
+		};
+		if width == 0 || height == 0 {
+			return Err(format!("Empty HDR image: {}x{}", width, height))
+		}
+
+		let (count, scanline_len) = match (width.checked_mul(height), width.checked_mul(4)) {
+			(Some(count), Some(scanline_len)) => (count, scanline_len),
+			_ => return Err(format!("HDR image too large: {}x{}", width, height)),
+		};
+		// scanlines too wide for run length encoding are stored flat, so the data has to hold them
+		if width >= 0x8000 && scanline_len > data.len() - pos {
+			return Err("Missing HDR pixel data".to_string())
+		}
+		// run length encoded data may be much smaller than the image it describes
+		let mut pixels = Vec::with_capacity(count.min(data.len()));
+		let mut scanline = vec![0u8; scanline_len];
+		for _ in 0..height {
+			let header = data.get(pos..pos + 4).ok_or("Missing HDR pixel data")?;
+			let rle = (8..0x8000).contains(&width) &&
+				header[0] == 2 &&
+				header[1] == 2 &&
+				((header[2] as usize) << 8 | header[3] as usize) == width;
+			if rle {
+				pos += 4;
+				// channels are stored one after another, each as runs or literal spans
+				for channel in 0..4 {
+					let mut x = 0;
+					while x < width {
+						let count = *data.get(pos).ok_or("Missing HDR pixel data")? as usize;
+						pos += 1;
+						let (run, count) =
+							if count > 128 { (true, count - 128) } else { (false, count) };
+						if count == 0 || x + count > width {
+							return Err("Corrupt HDR scanline".to_string())
+						}
+						for i in 0..count {
+							let value = *data.get(pos).ok_or("Missing HDR pixel data")?;
+							if !run {
+								pos += 1;
+							}
+							scanline[(x + i) * 4 + channel] = value;
+						}
+						if run {
+							pos += 1;
+						}
+						x += count;
+					}
+				}
+			} else {
+				let flat = data[pos..].get(..scanline_len).ok_or("Missing HDR pixel data")?;
+				scanline.copy_from_slice(flat);
+				pos += scanline_len;
+			}
+
+			pixels.extend(scanline.chunks(4).map(|rgbe| {
+				if rgbe[3] == 0 {
+					Color::new(0.0, 0.0, 0.0)
+				} else {
+					let scale = 2.0_f64.powi(rgbe[3] as i32 - (128 + 8));
+					Color::new(
+						rgbe[0] as f64 * scale,
+						rgbe[1] as f64 * scale,
+						rgbe[2] as f64 * scale,
+					)
+				}
+			}));
+		}
+		Ok(Canvas { pixels, width, height })
+	}
+
 	pub fn to_ppm(&self, max_color_value: u32, path: &str) {
 		// Construct header
 		let mut ppm_data = format!("P3\n{} {}\n{}\n", self.width, self.height, max_color_value);
@@ -61,6 +233,100 @@ impl Canvas {
 		};
 		write!(output, "{}", &ppm_data).expect("Couldn't write to the file");
 	}
+
+	pub fn from_pfm(path: &str) -> Result<Self, String> {
+		let data =
+			std::fs::read(path).map_err(|e| format!("Couldn't read {}. Error msg: {}", path, e))?;
+		Self::from_pfm_bytes(&data)
+	}
+
+	// Parses colour (PF) portable float maps of either byte order
+	pub fn from_pfm_bytes(data: &[u8]) -> Result<Self, String> {
+		let mut pos = 0;
+		let mut header = Vec::with_capacity(4);
+		while header.len() < 4 {
+			header.push(next_ppm_token(data, &mut pos).ok_or("Incomplete PFM header")?);
+		}
+		if header[0] != "PF" {
+			return Err(format!("Unsupported PFM format: {}", header[0]))
+		}
+		let parse = |token: &str| -> Result<usize, String> {
+			token.parse::<usize>().map_err(|_| format!("Invalid PFM value: {}", token))
+		};
+		let (width, height) = (parse(&header[1])?, parse(&header[2])?);
+		if width == 0 || height == 0 {
+			return Err(format!("Empty PFM image: {}x{}", width, height))
+		}
+		let scale = header[3]
+			.parse::<f64>()
+			.map_err(|_| format!("Invalid PFM scale: {}", header[3]))?;
+
+		let start = pos + 1;
+		let len = width
+			.checked_mul(height)
+			.and_then(|n| n.checked_mul(12))
+			.ok_or(format!("PFM image too large: {}x{}", width, height))?;
+		let raster =
+			data.get(start..).and_then(|d| d.get(..len)).ok_or("Missing PFM pixel data")?;
+		let values: Vec<f64> = raster
+			.chunks(4)
+			.map(|b| {
+				let bytes = [b[0], b[1], b[2], b[3]];
+				// a negative scale marks little endian data
+				let v =
+					if scale < 0.0 { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) };
+				v as f64
+			})
+			.collect();
+
+		let mut canvas = Canvas::new(width, height);
+		for (i, c) in values.chunks(3).enumerate() {
+			// rows are stored from the bottom up
+			canvas.write_pixel(i % width, height - 1 - i / width, Color::new(c[0], c[1], c[2]));
+		}
+		Ok(canvas)
+	}
+
+	// Portable float map with little endian values. Unlike PPM nothing is clamped, which suits
+	// HDR renders and data such as depth or normals.
+	pub fn to_pfm_bytes(&self) -> Vec<u8> {
+		let mut bytes = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+		for y in (0..self.height).rev() {
+			for x in 0..self.width {
+				let p = self.pixel_at(x, y);
+				for v in [p.red, p.green, p.blue] {
+					bytes.extend_from_slice(&(v as f32).to_le_bytes());
+				}
+			}
+		}
+		bytes
+	}
+
+	pub fn to_pfm(&self, path: &str) -> Result<(), String> {
+		std::fs::write(path, self.to_pfm_bytes())
+			.map_err(|e| format!("Couldn't write {}. Error msg: {}", path, e))
+	}
+}
+
+// Next whitespace separated token, skipping `#` comments
+fn next_ppm_token(data: &[u8], pos: &mut usize) -> Option<String> {
+	loop {
+		while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+			*pos += 1;
+		}
+		if *pos < data.len() && data[*pos] == b'#' {
+			while *pos < data.len() && data[*pos] != b'\n' {
+				*pos += 1;
+			}
+		} else {
+			break
+		}
+	}
+	let start = *pos;
+	while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+		*pos += 1;
+	}
+	(start < *pos).then(|| String::from_utf8_lossy(&data[start..*pos]).into_owned())
 }
 
 #[cfg(test)]
@@ -111,4 +377,128 @@ mod tests {
 
 		c.to_ppm(255, "test.ppm");
 	}
+
+	#[test]
+	fn reading_ppm() {
+		// plain format with comments
+		let data = b"P3\n# a comment\n2 1\n# another one\n255\n255 0 0  0 51 255\n";
+		let c = Canvas::from_ppm_bytes(data).unwrap();
+		assert_eq!((c.width, c.height), (2, 1));
+		assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.0, 0.0));
+		assert_eq!(c.pixel_at(1, 0), Color::new(0.0, 0.2, 1.0));
+
+		// binary format
+		let mut data = b"P6\n1 2\n255\n".to_vec();
+		data.extend_from_slice(&[0, 255, 0, 255, 255, 255]);
+		let c = Canvas::from_ppm_bytes(&data).unwrap();
+		assert_eq!(c.pixel_at(0, 0), Color::new(0.0, 1.0, 0.0));
+		assert_eq!(c.pixel_at(0, 1), Color::new(1.0, 1.0, 1.0));
+
+		// round trip through a file
+		let mut c = Canvas::new(3, 2);
+		c.write_pixel(2, 1, Color::new(1.0, 0.6, 0.2));
+		c.to_ppm(255, "test_read.ppm");
+		let read = Canvas::from_ppm("test_read.ppm").unwrap();
+		std::fs::remove_file("test_read.ppm").unwrap();
+		assert_eq!(read, c);
+
+		assert!(Canvas::from_ppm_bytes(b"P5\n1 1\n255\n0").is_err());
+		assert!(Canvas::from_ppm_bytes(b"P3\n2 2\n255\n0 0 0").is_err());
+		assert!(Canvas::from_ppm("missing.ppm").is_err());
+	}
+
+	#[test]
+	fn pfm_test() {
+		let mut c = Canvas::new(2, 2);
+		c.write_pixel(0, 0, Color::new(1.5, -2.0, 0.25));
+		c.write_pixel(1, 1, Color::new(1000.0, 0.0, 0.5));
+		let bytes = c.to_pfm_bytes();
+		assert!(bytes.starts_with(b"PF\n2 2\n-1.0\n"));
+		assert_eq!(bytes.len(), 12 + 2 * 2 * 12);
+		// the bottom row comes first
+		assert_eq!(bytes[12 + 12..12 + 16], 1000.0f32.to_le_bytes());
+		assert_eq!(Canvas::from_pfm_bytes(&bytes).unwrap(), c);
+
+		// big endian data
+		let mut data = b"PF\n1 1\n1.0\n".to_vec();
+		for v in [0.5f32, 2.0, -1.0] {
+			data.extend_from_slice(&v.to_be_bytes());
+		}
+		assert_eq!(
+			Canvas::from_pfm_bytes(&data).unwrap().pixel_at(0, 0),
+			Color::new(0.5, 2.0, -1.0)
+		);
+
+		c.to_pfm("test_read.pfm").unwrap();
+		let read = Canvas::from_pfm("test_read.pfm").unwrap();
+		std::fs::remove_file("test_read.pfm").unwrap();
+		assert_eq!(read, c);
+
+		assert!(Canvas::from_pfm_bytes(b"Pf\n1 1\n-1.0\n").is_err());
+		assert!(Canvas::from_pfm_bytes(b"PF\n1 1\n-1.0\n\0\0").is_err());
+	}
+
+	#[test]
+	fn reading_hdr() {
+		// flat scanlines
+		let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 1\n".to_vec();
+		data.extend_from_slice(&[128, 64, 0, 129, 128, 128, 128, 140]);
+		let c = Canvas::from_hdr_bytes(&data).unwrap();
+		assert_eq!((c.width, c.height), (1, 2));
+		assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.5, 0.0));
+		assert_eq!(c.pixel_at(0, 1), Color::new(2048.0, 2048.0, 2048.0));
+
+		// run length encoded scanline of 8 pixels
+		let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+		data.extend_from_slice(&[2, 2, 0, 8]);
+		// red: a run of 8
+		data.extend_from_slice(&[136, 128]);
+		// green: 4 literals followed by a run of 4
+		data.extend_from_slice(&[4, 0, 32, 64, 96, 132, 128]);
+		// blue: a run of 8 zeros
+		data.extend_from_slice(&[136, 0]);
+		// exponent: a run of 8
+		data.extend_from_slice(&[136, 129]);
+		let c = Canvas::from_hdr_bytes(&data).unwrap();
+		assert_eq!(c.pixel_at(0, 0), Color::new(1.0, 0.0, 0.0));
+		assert_eq!(c.pixel_at(2, 0), Color::new(1.0, 0.5, 0.0));
+		assert_eq!(c.pixel_at(7, 0), Color::new(1.0, 1.0, 0.0));
+
+		assert!(Canvas::from_hdr_bytes(b"P3\n1 1\n255\n0 0 0").is_err());
+		assert!(Canvas::from_hdr_bytes(b"#?RADIANCE\n\n+Y 1 +X 1\n\x80\x80\x80\x81").is_err());
+		assert!(Canvas::from_hdr_bytes(b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80").is_err());
+	}
+
+	#[test]
+	fn huge_header_test() {
+		// sizes whose byte counts overflow are errors rather than panics
+		let huge = "4294967296 4294967296";
+		assert!(Canvas::from_ppm_bytes(format!("P6\n{}\n255\n\0", huge).as_bytes()).is_err());
+		assert!(Canvas::from_ppm_bytes(format!("P3\n{}\n255\n0", huge).as_bytes()).is_err());
+		assert!(Canvas::from_pfm_bytes(format!("PF\n{}\n-1.0\n\0", huge).as_bytes()).is_err());
+		let max = usize::MAX;
+		assert!(Canvas::from_ppm_bytes(format!("P6\n{} 1\n65535\n\0", max).as_bytes()).is_err());
+		let hdr = |w: usize, h: usize| format!("#?RADIANCE\n\n-Y {} +X {}\n\0\0\0\0", h, w);
+		assert!(Canvas::from_hdr_bytes(hdr(4294967296, 4294967296).as_bytes()).is_err());
+		assert!(Canvas::from_hdr_bytes(hdr(max, 1).as_bytes()).is_err());
+
+		// large but valid sizes are not allocated up front when the data can not hold them
+		assert!(Canvas::from_hdr_bytes(hdr(1 << 40, 1).as_bytes()).is_err());
+		assert!(Canvas::from_hdr_bytes(hdr(100, 1 << 40).as_bytes()).is_err());
+	}
+
+	#[test]
+	fn empty_image_test() {
+		// textures can not be sampled without any pixels, so empty images are errors
+		for (w, h) in [(0, 0), (0, 2), (2, 0)] {
+			let ppm = format!("P3\n{} {}\n255\n", w, h);
+			assert!(Canvas::from_ppm_bytes(ppm.as_bytes()).is_err());
+			let ppm = format!("P6\n{} {}\n255\n", w, h);
+			assert!(Canvas::from_ppm_bytes(ppm.as_bytes()).is_err());
+			let pfm = format!("PF\n{} {}\n-1.0\n", w, h);
+			assert!(Canvas::from_pfm_bytes(pfm.as_bytes()).is_err());
+			let hdr = format!("#?RADIANCE\n\n-Y {} +X {}\n", h, w);
+			assert!(Canvas::from_hdr_bytes(hdr.as_bytes()).is_err());
+		}
+	}
 }
@@ -0,0 +1,249 @@
+use crate::{
+	primitives::color::Color,
+	visualisation::{
+		aov::{Aov, RenderOutput},
+		canvas::Canvas,
+	},
+};
+
+// B3 spline taps of the À-Trous wavelet, spread further apart on every pass
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Edge aware À-Trous wavelet filter. Every pass blurs with a 5x5 kernel whose taps are twice as
+// far apart as in the previous one, while neighbours whose colour, normal, albedo or depth differ
+// too much are ignored so edges stay sharp.
+// reference: https://jo.dreggn.org/home/2010_atrous.pdf
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+	// colour difference tolerated between neighbours, 0 leaves the image untouched
+	pub strength: f64,
+	pub iterations: usize,
+	// tolerances of the guides, 0 only mixes neighbours whose guide values are identical
+	pub normal_sigma: f64,
+	pub albedo_sigma: f64,
+	pub depth_sigma: f64,
+}
+
+// Feature buffers guiding the filter, missing ones do not stop it anywhere
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Guides<'a> {
+	pub normal: Option<&'a Canvas>,
+	pub albedo: Option<&'a Canvas>,
+	pub depth: Option<&'a Canvas>,
+}
+
+impl Denoiser {
+	pub fn new(strength: f64) -> Self {
+		Self { strength, iterations: 5, normal_sigma: 0.1, albedo_sigma: 0.1, depth_sigma: 0.1 }
+	}
+
+	// Filters the beauty image of a render using whichever guide AOVs were rendered with it
+	pub fn denoise_output(&self, output: &RenderOutput) -> Result<Canvas, String> {
+		let guides = Guides {
+			normal: output.buffer(Aov::Normal),
+			albedo: output.buffer(Aov::Albedo),
+			depth: output.buffer(Aov::Depth),
+		};
+		self.denoise(&output.beauty, &guides)
+	}
+
+	pub fn denoise(&self, image: &Canvas, guides: &Guides) -> Result<Canvas, String> {
+		for guide in [guides.normal, guides.albedo, guides.depth].into_iter().flatten() {
+			if (guide.width, guide.height) != (image.width, image.height) {
+				return Err(format!(
+					"Guide of size {}x{} does not match image of size {}x{}",
+					guide.width, guide.height, image.width, image.height
+				))
+			}
+		}
+		if self.strength <= 0.0 {
+			return Ok(image.clone())
+		}
+
+		let mut current = image.clone();
+		// passes whose taps all fall outside the image would leave it as it is
+		let extent = image.width.max(image.height);
+		let steps = (0..self.iterations).map_while(|i| 1usize.checked_shl(i.try_into().ok()?));
+		for step in steps.take_while(|&step| step < extent) {
+			// later passes mix pixels further apart and are kept from blurring as much
+			current = self.pass(&current, guides, step, self.strength / step as f64);
+		}
+		Ok(current)
+	}
+
+	fn pass(&self, image: &Canvas, guides: &Guides, step: usize, color_sigma: f64) -> Canvas {
+		let (width, height) = (image.width as isize, image.height as isize);
+		let mut filtered = Canvas::new(image.width, image.height);
+		for y in 0..height {
+			for x in 0..width {
+				let p = (x as usize, y as usize);
+				let center = image.pixel_at(p.0, p.1);
+				let mut sum = Color::new(0.0, 0.0, 0.0);
+				let mut total = 0.0;
+				for (j, ky) in KERNEL.iter().enumerate() {
+					for (i, kx) in KERNEL.iter().enumerate() {
+						let qx = x + (i as isize - 2) * step as isize;
+						let qy = y + (j as isize - 2) * step as isize;
+						if qx < 0 || qy < 0 || qx >= width || qy >= height {
+							continue
+						}
+						let q = (qx as usize, qy as usize);
+						let color = image.pixel_at(q.0, q.1);
+						let mut weight = kx * ky * edge_weight(center, color, color_sigma);
+						if let Some(normal) = guides.normal {
+							let (a, b) = (normal.pixel_at(p.0, p.1), normal.pixel_at(q.0, q.1));
+							weight *= edge_weight(a, b, self.normal_sigma);
+						}
+						if let Some(albedo) = guides.albedo {
+							let (a, b) = (albedo.pixel_at(p.0, p.1), albedo.pixel_at(q.0, q.1));
+							weight *= edge_weight(a, b, self.albedo_sigma);
+						}
+						if let Some(depth) = guides.depth {
+							let (a, b) =
+								(depth.pixel_at(p.0, p.1).red, depth.pixel_at(q.0, q.1).red);
+							weight *= depth_weight(a, b, self.depth_sigma);
+						}
+						sum = sum + color * weight;
+						total += weight;
+					}
+				}
+				// the centre tap always has a positive weight
+				filtered.write_pixel(p.0, p.1, sum * (1.0 / total));
+			}
+		}
+		filtered
+	}
+}
+
+fn edge_weight(a: Color, b: Color, sigma: f64) -> f64 {
+	let d = a - b;
+	let distance = d.red * d.red + d.green * d.green + d.blue * d.blue;
+	if sigma <= 0.0 {
+		return if distance == 0.0 { 1.0 } else { 0.0 }
+	}
+	(-distance / (sigma * sigma)).exp()
+}
+
+// relative to the depth, so far away surfaces are not cut into pieces; misses have infinite depth
+// and only mix with each other
+fn depth_weight(a: f64, b: f64, sigma: f64) -> f64 {
+	if a.is_infinite() || b.is_infinite() || sigma <= 0.0 {
+		return if a == b { 1.0 } else { 0.0 }
+	}
+	(-(a - b).abs() / (sigma * a.abs().max(b.abs()).max(1e-9))).exp()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::sampler::Sampler;
+
+	// two flat halves of 0.2 and 0.8 with noise on top
+	fn noisy_image(size: usize, noise: f64) -> (Canvas, Canvas) {
+		let mut sampler = Sampler::new(11);
+		let mut image = Canvas::new(size, size);
+		let mut normal = Canvas::new(size, size);
+		for y in 0..size {
+			for x in 0..size {
+				let (base, n) = if x < size / 2 {
+					(0.2, Color::new(1.0, 0.0, 0.0))
+				} else {
+					(0.8, Color::new(0.0, 1.0, 0.0))
+				};
+				let v = base + (sampler.next_f64() - 0.5) * noise;
+				image.write_pixel(x, y, Color::new(v, v, v));
+				normal.write_pixel(x, y, n);
+			}
+		}
+		(image, normal)
+	}
+
+	fn half_statistics(image: &Canvas, left: bool) -> (f64, f64) {
+		let values: Vec<f64> = (0..image.height)
+			.flat_map(|y| (0..image.width).map(move |x| (x, y)))
+			.filter(|&(x, _)| (x < image.width / 2) == left)
+			.map(|(x, y)| image.pixel_at(x, y).red)
+			.collect();
+		let mean = values.iter().sum::<f64>() / values.len() as f64;
+		let variance =
+			values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64;
+		(mean, variance)
+	}
+
+	#[test]
+	fn denoise_test() {
+		let (image, normal) = noisy_image(32, 0.4);
+		let guides = Guides { normal: Some(&normal), ..Guides::default() };
+		let denoised = Denoiser::new(0.5).denoise(&image, &guides).unwrap();
+
+		// noise is mostly gone while the halves keep their own level right up to the edge
+		for left in [true, false] {
+			let (mean, before) = half_statistics(&image, left);
+			let (filtered_mean, after) = half_statistics(&denoised, left);
+			assert!(after < before * 0.1, "{} -> {}", before, after);
+			approx::assert_relative_eq!(filtered_mean, mean, epsilon = 0.01);
+		}
+		for y in 0..32 {
+			assert!((denoised.pixel_at(15, y).red - 0.2).abs() < 0.1);
+			assert!((denoised.pixel_at(16, y).red - 0.8).abs() < 0.1);
+		}
+
+		// render outputs provide their own guides
+		let output =
+			RenderOutput { beauty: image.clone(), buffers: vec![(Aov::Normal, normal.clone())] };
+		assert_eq!(Denoiser::new(0.5).denoise_output(&output).unwrap(), denoised);
+
+		// without a guide a weak filter still keeps the strong edge thanks to the colour term
+		let denoised = Denoiser::new(0.2).denoise(&image, &Guides::default()).unwrap();
+		assert!(half_statistics(&denoised, true).1 < half_statistics(&image, true).1);
+		assert!((denoised.pixel_at(16, 0).red - 0.8).abs() < 0.15);
+
+		// passes beyond the size of the image are skipped instead of overflowing the step
+		let many = Denoiser { iterations: 1000, ..Denoiser::new(0.5) };
+		let five = Denoiser { iterations: 5, ..many };
+		assert_eq!(many.denoise(&image, &guides).unwrap(), five.denoise(&image, &guides).unwrap());
+
+		// zero strength leaves the image alone
+		assert_eq!(Denoiser::new(0.0).denoise(&image, &guides).unwrap(), image);
+
+		let small = Canvas::new(4, 4);
+		let guides = Guides { depth: Some(&small), ..Guides::default() };
+		assert!(Denoiser::new(0.5).denoise(&image, &guides).is_err());
+	}
+
+	#[test]
+	fn depth_weight_test() {
+		assert_eq!(depth_weight(f64::INFINITY, f64::INFINITY, 0.1), 1.0);
+		assert_eq!(depth_weight(f64::INFINITY, 3.0, 0.1), 0.0);
+		assert_eq!(depth_weight(2.0, 2.0, 0.1), 1.0);
+		// the same gap matters less further away
+		assert!(depth_weight(100.0, 101.0, 0.1) > depth_weight(1.0, 2.0, 0.1));
+		// no tolerance is a hard edge
+		assert_eq!(depth_weight(2.0, 2.0, 0.0), 1.0);
+		assert_eq!(depth_weight(2.0, 2.5, 0.0), 0.0);
+	}
+
+	#[test]
+	fn zero_sigma_test() {
+		let (a, b) = (Color::new(0.5, 0.5, 0.5), Color::new(0.5, 0.6, 0.5));
+		assert_eq!(edge_weight(a, a, 0.0), 1.0);
+		assert_eq!(edge_weight(a, b, 0.0), 0.0);
+		assert_eq!(edge_weight(a, b, -1.0), 0.0);
+
+		// guides without any tolerance keep the halves apart but still filter within them
+		let (image, normal) = noisy_image(16, 0.4);
+		let mut depth = Canvas::new(16, 16);
+		depth.pixels.iter_mut().for_each(|p| *p = Color::new(3.0, 3.0, 3.0));
+		let guides = Guides { normal: Some(&normal), albedo: Some(&normal), depth: Some(&depth) };
+		let denoiser = Denoiser {
+			normal_sigma: 0.0,
+			albedo_sigma: 0.0,
+			depth_sigma: 0.0,
+			..Denoiser::new(0.5)
+		};
+		let denoised = denoiser.denoise(&image, &guides).unwrap();
+		assert!(denoised.pixels.iter().all(|p| p.red.is_finite()));
+		assert!(half_statistics(&denoised, true).1 < half_statistics(&image, true).1);
+		assert!((denoised.pixel_at(8, 0).red - 0.8).abs() < 0.15);
+	}
+}
@@ -0,0 +1,76 @@
+// Wavelengths in nanometres standing in for the red, green and blue channels
+pub const CHANNEL_WAVELENGTHS: [f64; 3] = [650.0, 550.0, 450.0];
+
+// Refractive index as a function of wavelength, both models take the wavelength in micrometres
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Dispersion {
+	// n = a + b / λ²
+	Cauchy { a: f64, b: f64 },
+	// n² = 1 + Σ b_i λ² / (λ² - c_i)
+	Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+	pub fn new_cauchy(a: f64, b: f64) -> Self {
+		Self::Cauchy { a, b }
+	}
+
+	pub fn new_sellmeier(b: [f64; 3], c: [f64; 3]) -> Self {
+		Self::Sellmeier { b, c }
+	}
+
+	// Schott N-BK7, the usual borosilicate crown glass
+	pub fn crown_glass() -> Self {
+		Self::new_sellmeier(
+			[1.039_612_12, 0.231_792_344, 1.010_469_45],
+			[0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+		)
+	}
+
+	// Schott SF11, a dense flint glass with strong dispersion
+	pub fn flint_glass() -> Self {
+		Self::new_sellmeier(
+			[1.737_596_95, 0.313_747_346, 1.898_781_01],
+			[0.013_188_707, 0.062_306_814_2, 155.236_29],
+		)
+	}
+
+	// Refractive index at a wavelength given in nanometres
+	pub fn index(&self, wavelength: f64) -> f64 {
+		let l2 = (wavelength / 1000.0).powi(2);
+		match self {
+			Self::Cauchy { a, b } => a + b / l2,
+			Self::Sellmeier { b, c } =>
+				(1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()).sqrt(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dispersion_test() {
+		// reference values of the d line at 587.6nm
+		approx::assert_relative_eq!(
+			Dispersion::crown_glass().index(587.56),
+			1.5168,
+			epsilon = 1e-4
+		);
+		approx::assert_relative_eq!(
+			Dispersion::flint_glass().index(587.56),
+			1.7847,
+			epsilon = 1e-4
+		);
+
+		let cauchy = Dispersion::new_cauchy(1.5, 0.01);
+		approx::assert_relative_eq!(cauchy.index(500.0), 1.54);
+
+		// normal dispersion, blue bends more than red
+		for dispersion in [cauchy, Dispersion::crown_glass(), Dispersion::flint_glass()] {
+			let [red, green, blue] = CHANNEL_WAVELENGTHS.map(|w| dispersion.index(w));
+			assert!(red < green && green < blue);
+		}
+	}
+}
@@ -0,0 +1,445 @@
+use crate::{
+	patterns::{
+		image_pattern::{ImagePattern, TextureAddressing},
+		uv_mapping::{cube_face, cube_face_uv, spherical_map, CubeFace, UvMapping},
+	},
+	primitives::{color::Color, point::Point, sampler::uniform_sample_sphere, vector::Vector},
+	visualisation::canvas::Canvas,
+};
+use std::{f64::consts::PI, path::Path, sync::Arc};
+
+// Direction towards the environment together with the radiance arriving from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSample {
+	pub direction: Vector,
+	pub radiance: Color,
+	// with respect to solid angle
+	pub pdf: f64,
+}
+
+// Radiance arriving from infinitely far away, seen by rays which miss every object
+#[derive(Debug, Clone, PartialEq)]
+pub enum Environment {
+	Constant(Color),
+	// blends from `bottom` straight down to `top` straight up
+	Gradient { top: Color, bottom: Color },
+	CubeMap(Box<CubeMap>),
+	Equirectangular(EnvironmentMap),
+}
+
+impl Default for Environment {
+	fn default() -> Self {
+		Self::Constant(Color::new(0.0, 0.0, 0.0))
+	}
+}
+
+impl Environment {
+	pub fn color_at(&self, direction: &Vector) -> Color {
+		let direction = direction.normalise();
+		match self {
+			Self::Constant(color) => *color,
+			Self::Gradient { top, bottom } => {
+				let t = 0.5 * (direction.tuple.y + 1.0);
+				*bottom * (1.0 - t) + *top * t
+			},
+			Self::CubeMap(map) => map.color_at(&direction),
+			Self::Equirectangular(map) => map.color_at(&direction),
+		}
+	}
+
+	// Picks a direction to light a surface from. Image maps are importance sampled by luminance,
+	// everything else uniformly over the sphere. A black constant background casts no light.
+	pub fn sample(&self, u1: f64, u2: f64) -> Option<EnvironmentSample> {
+		match self {
+			Self::Constant(color) if *color == Color::new(0.0, 0.0, 0.0) => None,
+			Self::Equirectangular(map) => map.sample(u1, u2),
+			_ => {
+				let direction = uniform_sample_sphere(u1, u2);
+				Some(EnvironmentSample {
+					direction,
+					radiance: self.color_at(&direction),
+					pdf: 1.0 / (4.0 * PI),
+				})
+			},
+		}
+	}
+}
+
+// Six images seen from the inside of a cube, each one upright when looking straight at it with
+// +y up (the up and down faces have +z towards the top and bottom of the image respectively)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeMap {
+	pub left: ImagePattern,
+	pub right: ImagePattern,
+	pub front: ImagePattern,
+	pub back: ImagePattern,
+	pub up: ImagePattern,
+	pub down: ImagePattern,
+}
+
+impl CubeMap {
+	pub fn new(
+		left: Canvas,
+		right: Canvas,
+		front: Canvas,
+		back: Canvas,
+		up: Canvas,
+		down: Canvas,
+	) -> Self {
+		let face = |canvas| {
+			let mut image = ImagePattern::new(canvas, UvMapping::Cube);
+			image.addressing = TextureAddressing::Clamp;
+			image
+		};
+		Self {
+			left: face(left),
+			right: face(right),
+			front: face(front),
+			back: face(back),
+			up: face(up),
+			down: face(down),
+		}
+	}
+
+	pub fn color_at(&self, direction: &Vector) -> Color {
+		let d = direction.tuple;
+		let scale = d.x.abs().max(d.y.abs()).max(d.z.abs());
+		let point = Point::new(d.x / scale, d.y / scale, d.z / scale);
+		let face = cube_face(&point);
+		let (u, v) = cube_face_uv(face, &point);
+		let image = match face {
+			CubeFace::Left => &self.left,
+			CubeFace::Right => &self.right,
+			CubeFace::Front => &self.front,
+			CubeFace::Back => &self.back,
+			CubeFace::Up => &self.up,
+			CubeFace::Down => &self.down,
+		};
+		image.uv_pattern_at(u, v)
+	}
+}
+
+// Piecewise constant density over the pixels of an equirectangular image, proportional to the
+// luminance of each pixel times the solid angle it covers
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentDistribution {
+	pub width: usize,
+	pub height: usize,
+	// probability of every pixel, row by row
+	pub weights: Vec<f64>,
+	// cumulative distribution over the rows, `height + 1` entries
+	pub marginal: Vec<f64>,
+	// cumulative distribution over the pixels of every row, `width + 1` entries each
+	pub conditional: Vec<Vec<f64>>,
+}
+
+impl EnvironmentDistribution {
+	pub fn new(canvas: &Canvas) -> Self {
+		let (width, height) = (canvas.width, canvas.height);
+		// bilinear lookups reach into the neighbouring pixels, so every pixel takes the
+		// brightest of its neighbourhood to keep the density positive wherever light comes from
+		let luminance = |x: i64, y: i64| {
+			let x = x.rem_euclid(width as i64) as usize;
+			let y = y.clamp(0, height as i64 - 1) as usize;
+			canvas.pixel_at(x, y).luminance().max(0.0)
+		};
+		let mut weights = Vec::with_capacity(width * height);
+		for y in 0..height {
+			let sin_theta = ((y as f64 + 0.5) * PI / height as f64).sin();
+			for x in 0..width {
+				let (x, y) = (x as i64, y as i64);
+				let mut brightest: f64 = 0.0;
+				for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+					brightest = brightest.max(luminance(x + dx, y + dy));
+				}
+				weights.push(brightest * sin_theta);
+			}
+		}
+		let total: f64 = weights.iter().sum();
+		if total > 0.0 {
+			weights.iter_mut().for_each(|w| *w /= total);
+		}
+
+		let cumulative = |values: &[f64]| {
+			let mut cdf = Vec::with_capacity(values.len() + 1);
+			cdf.push(0.0);
+			for value in values {
+				cdf.push(cdf.last().unwrap() + value);
+			}
+			cdf
+		};
+		let rows: Vec<f64> = weights.chunks(width).map(|row| row.iter().sum()).collect();
+		let marginal = cumulative(&rows);
+		let conditional = weights
+			.chunks(width)
+			.zip(&rows)
+			.map(|(row, sum)| {
+				let cdf = cumulative(row);
+				cdf.iter().map(|c| if *sum > 0.0 { c / sum } else { 0.0 }).collect()
+			})
+			.collect();
+		Self { width, height, weights, marginal, conditional }
+	}
+
+	// Picks a pixel proportionally to its weight, returns the continuous (u, v) inside it
+	pub fn sample(&self, u1: f64, u2: f64) -> Option<(f64, f64)> {
+		let total = *self.marginal.last()?;
+		if total <= 0.0 {
+			return None
+		}
+		let (y, dy) = invert_cdf(&self.marginal, u2 * total);
+		let (x, dx) = invert_cdf(&self.conditional[y], u1);
+		Some(((x as f64 + dx) / self.width as f64, 1.0 - (y as f64 + dy) / self.height as f64))
+	}
+
+	// Density of `sample` with respect to the area of the unit uv square
+	pub fn pdf(&self, u: f64, v: f64) -> f64 {
+		let x = ((u * self.width as f64) as usize).min(self.width - 1);
+		let y = (((1.0 - v) * self.height as f64) as usize).min(self.height - 1);
+		self.weights[y * self.width + x] * (self.width * self.height) as f64
+	}
+}
+
+// Index of the interval of `cdf` containing `value`, with the position inside it
+fn invert_cdf(cdf: &[f64], value: f64) -> (usize, f64) {
+	let index = (cdf.partition_point(|c| *c <= value).max(1) - 1).min(cdf.len() - 2);
+	// skip empty intervals
+	let mut index = index;
+	while index > 0 && cdf[index + 1] <= cdf[index] {
+		index -= 1;
+	}
+	let width = cdf[index + 1] - cdf[index];
+	let offset = if width > 0.0 { ((value - cdf[index]) / width).clamp(0.0, 1.0) } else { 0.5 };
+	(index, offset)
+}
+
+// Latitude/longitude image covering the whole sphere of directions, +z in the middle. The
+// sampling distribution is built from the image once, so replace the whole map rather than
+// editing its pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+	pub image: ImagePattern,
+	pub intensity: f64,
+	pub distribution: Arc<EnvironmentDistribution>,
+}
+
+impl EnvironmentMap {
+	pub fn new(canvas: Canvas) -> Result<Self, String> {
+		if canvas.width == 0 || canvas.height == 0 {
+			return Err(format!("Empty environment map: {}x{}", canvas.width, canvas.height))
+		}
+		let distribution = Arc::new(EnvironmentDistribution::new(&canvas));
+		Ok(Self {
+			image: ImagePattern::new(canvas, UvMapping::Spherical),
+			intensity: 1.0,
+			distribution,
+		})
+	}
+
+	// Loads `.hdr` (Radiance RGBE) or `.ppm` images
+	pub fn load(path: &str) -> Result<Self, String> {
+		let extension = Path::new(path)
+			.extension()
+			.and_then(|e| e.to_str())
+			.map(|e| e.to_ascii_lowercase());
+		let canvas = match extension.as_deref() {
+			Some("hdr") => Canvas::from_hdr(path)?,
+			Some("ppm") => Canvas::from_ppm(path)?,
+			_ => return Err(format!("Unsupported environment map: {}", path)),
+		};
+		Self::new(canvas)
+	}
+
+	pub fn color_at(&self, direction: &Vector) -> Color {
+		let d = direction.tuple;
+		let (u, v) = spherical_map(&Point::new(d.x, d.y, d.z));
+		// keep the poles from blending with the opposite edge of the image
+		let half_texel = 0.5 / self.image.canvas.height as f64;
+		self.image.uv_pattern_at(u, v.clamp(half_texel, 1.0 - half_texel)) * self.intensity
+	}
+
+	pub fn sample(&self, u1: f64, u2: f64) -> Option<EnvironmentSample> {
+		let (u, v) = self.distribution.sample(u1, u2)?;
+		// inverse of the spherical mapping
+		let theta = (0.5 - u) * 2.0 * PI;
+		let phi = (1.0 - v) * PI;
+		let sin_phi = phi.sin();
+		if sin_phi <= 0.0 {
+			return None
+		}
+		let direction = Vector::new(sin_phi * theta.sin(), phi.cos(), sin_phi * theta.cos());
+		// the uv square maps onto the sphere with dw = 2 * PI * PI * sin(phi) du dv
+		let pdf = self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_phi);
+		if pdf <= 0.0 {
+			return None
+		}
+		Some(EnvironmentSample { direction, radiance: self.color_at(&direction), pdf })
+	}
+
+	// Density of `sample` picking `direction`, with respect to solid angle
+	pub fn pdf(&self, direction: &Vector) -> f64 {
+		let d = direction.normalise().tuple;
+		let (u, v) = spherical_map(&Point::new(d.x, d.y, d.z));
+		let sin_phi = (1.0 - d.y * d.y).max(0.0).sqrt();
+		if sin_phi <= 0.0 {
+			return 0.0
+		}
+		self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_phi)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn constant_canvas(color: Color) -> Canvas {
+		let mut canvas = Canvas::new(2, 2);
+		canvas.pixels.iter_mut().for_each(|p| *p = color);
+		canvas
+	}
+
+	#[test]
+	fn gradient_test() {
+		let env = Environment::Gradient {
+			top: Color::new(0.5, 0.7, 1.0),
+			bottom: Color::new(1.0, 1.0, 1.0),
+		};
+		assert_eq!(env.color_at(&Vector::new(0.0, 1.0, 0.0)), Color::new(0.5, 0.7, 1.0));
+		assert_eq!(env.color_at(&Vector::new(0.0, -2.0, 0.0)), Color::new(1.0, 1.0, 1.0));
+		assert_eq!(env.color_at(&Vector::new(1.0, 0.0, 0.0)), Color::new(0.75, 0.85, 1.0));
+		assert_eq!(
+			Environment::default().color_at(&Vector::new(1.0, 0.0, 0.0)),
+			Color::new(0.0, 0.0, 0.0)
+		);
+	}
+
+	#[test]
+	fn cube_map_test() {
+		let colors = [
+			Color::new(1.0, 0.0, 0.0),
+			Color::new(0.0, 1.0, 0.0),
+			Color::new(0.0, 0.0, 1.0),
+			Color::new(1.0, 1.0, 0.0),
+			Color::new(0.0, 1.0, 1.0),
+			Color::new(1.0, 0.0, 1.0),
+		];
+		let env = Environment::CubeMap(Box::new(CubeMap::new(
+			constant_canvas(colors[0]),
+			constant_canvas(colors[1]),
+			constant_canvas(colors[2]),
+			constant_canvas(colors[3]),
+			constant_canvas(colors[4]),
+			constant_canvas(colors[5]),
+		)));
+		for (direction, color) in [
+			(Vector::new(-1.0, 0.2, 0.3), colors[0]),
+			(Vector::new(3.0, -0.5, 0.1), colors[1]),
+			(Vector::new(0.1, 0.1, 1.0), colors[2]),
+			(Vector::new(0.2, -0.3, -0.5), colors[3]),
+			(Vector::new(0.0, 1.0, 0.0), colors[4]),
+			(Vector::new(0.5, -2.0, 0.5), colors[5]),
+		] {
+			assert_eq!(env.color_at(&direction), color);
+		}
+
+		// faces are upright when seen from inside the cube
+		let mut front = constant_canvas(Color::new(0.0, 0.0, 0.0));
+		front.write_pixel(1, 0, Color::new(1.0, 1.0, 1.0));
+		let mut map = CubeMap::new(
+			constant_canvas(colors[0]),
+			constant_canvas(colors[1]),
+			front,
+			constant_canvas(colors[3]),
+			constant_canvas(colors[4]),
+			constant_canvas(colors[5]),
+		);
+		map.front.filter = crate::patterns::image_pattern::TextureFilter::Nearest;
+		assert_eq!(map.color_at(&Vector::new(0.5, 0.5, 1.0)), Color::new(1.0, 1.0, 1.0));
+		assert_eq!(map.color_at(&Vector::new(-0.5, 0.5, 1.0)), Color::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn equirectangular_test() {
+		// left half of the image is dark, the right one bright
+		let mut canvas = Canvas::new(4, 2);
+		for y in 0..2 {
+			canvas.write_pixel(2, y, Color::new(1.0, 1.0, 1.0));
+			canvas.write_pixel(3, y, Color::new(1.0, 1.0, 1.0));
+		}
+		let mut map = EnvironmentMap::new(canvas).unwrap();
+		map.intensity = 2.0;
+		let env = Environment::Equirectangular(map);
+
+		// u grows counter-clockwise from -z when seen from above, +z sits in the middle
+		assert_eq!(env.color_at(&Vector::new(-1.0, 0.0, 0.0)), Color::new(2.0, 2.0, 2.0));
+		assert_eq!(env.color_at(&Vector::new(1.0, 0.0, 0.0)), Color::new(0.0, 0.0, 0.0));
+		assert_eq!(env.color_at(&Vector::new(0.0, 0.0, 1.0)), Color::new(1.0, 1.0, 1.0));
+
+		// poles do not pick up the other edge of the image
+		assert_eq!(env.color_at(&Vector::new(-1.0, 1e3, 0.0)), Color::new(2.0, 2.0, 2.0));
+	}
+
+	#[test]
+	fn importance_sampling_test() {
+		use crate::primitives::sampler::Sampler;
+
+		// dark sky with a small bright patch
+		let mut canvas = Canvas::new(16, 8);
+		canvas.pixels.iter_mut().for_each(|p| *p = Color::new(0.1, 0.1, 0.1));
+		canvas.write_pixel(5, 2, Color::new(50.0, 40.0, 30.0));
+		let map = EnvironmentMap::new(canvas).unwrap();
+
+		// the bright pixel gets most of the samples, and densities agree with `pdf`
+		let mut sampler = Sampler::new(3);
+		let n = 50000;
+		let (mut bright, mut estimate, mut uniform) = (0, 0.0, 0.0);
+		for _ in 0..n {
+			let (u1, u2) = sampler.next_2d();
+			let sample = map.sample(u1, u2).unwrap();
+			approx::assert_relative_eq!(
+				sample.pdf,
+				map.pdf(&sample.direction),
+				max_relative = 1e-6
+			);
+			if sample.radiance.red > 1.0 {
+				bright += 1;
+			}
+			estimate += sample.radiance.luminance() / sample.pdf;
+
+			let (u1, u2) = sampler.next_2d();
+			let direction = uniform_sample_sphere(u1, u2);
+			uniform += map.color_at(&direction).luminance() * 4.0 * PI;
+		}
+		assert!(bright > n / 4);
+
+		// importance sampled and uniform estimates of the total power agree
+		let (estimate, uniform) = (estimate / n as f64, uniform / n as f64);
+		approx::assert_relative_eq!(estimate, uniform, max_relative = 0.1);
+
+		// black images cannot be sampled
+		assert_eq!(EnvironmentMap::new(Canvas::new(4, 2)).unwrap().sample(0.5, 0.5), None);
+		assert_eq!(Environment::default().sample(0.5, 0.5), None);
+		let constant = Environment::Constant(Color::new(1.0, 1.0, 1.0)).sample(0.5, 0.5).unwrap();
+		approx::assert_relative_eq!(constant.pdf, 1.0 / (4.0 * PI));
+	}
+
+	#[test]
+	fn load_test() {
+		let mut canvas = Canvas::new(2, 1);
+		canvas.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+		canvas.to_ppm(255, "test_env.ppm");
+		let map = EnvironmentMap::load("test_env.ppm");
+		std::fs::remove_file("test_env.ppm").unwrap();
+		assert_eq!(*map.unwrap().image.canvas, canvas);
+
+		assert!(EnvironmentMap::load("sky.png").is_err());
+		assert!(EnvironmentMap::load("missing.hdr").is_err());
+
+		// images without pixels have nothing to sample
+		std::fs::write("test_env_empty.ppm", "P3\n0 0\n255\n").unwrap();
+		let map = EnvironmentMap::load("test_env_empty.ppm");
+		std::fs::remove_file("test_env_empty.ppm").unwrap();
+		assert!(map.is_err());
+		assert!(EnvironmentMap::new(Canvas::new(0, 2)).is_err());
+	}
+}
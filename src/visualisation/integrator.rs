@@ -0,0 +1,595 @@
+use crate::{
+	intersection::hit,
+	primitives::{
+		color::Color,
+		point::Point,
+		ray::Ray,
+		sampler::{cosine_sample_hemisphere, Sampler},
+		vector::Vector,
+	},
+	visualisation::{intersection::Lobe, medium::distance_inside, world::World},
+};
+use std::f64::consts::PI;
+
+// number of bounces before Russian roulette may start terminating paths
+const RUSSIAN_ROULETTE_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Integrator {
+	// recursive Phong shading with a fixed recursion depth, see `World::color_at`
+	#[default]
+	Whitted,
+	PathTracer(PathTracer),
+	// grey image of how open the first surface seen through each pixel is
+	AmbientOcclusion(AmbientOcclusion),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+	pub samples: usize,
+	pub max_depth: usize,
+	pub seed: u64,
+}
+
+// Estimates how much of the hemisphere above a point is free of geometry within `max_distance`,
+// with `samples` cosine weighted rays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientOcclusion {
+	pub samples: usize,
+	pub max_distance: f64,
+	pub seed: u64,
+}
+
+impl AmbientOcclusion {
+	pub fn new(samples: usize, max_distance: f64) -> Self {
+		Self { samples, max_distance, seed: 0 }
+	}
+
+	// Fraction of the rays leaving `point` around `normal` which escape, 1 in the open and 0 in a
+	// closed crevice. Transparent objects only hold back what they do not let through.
+	pub fn visibility(
+		&self,
+		world: &World,
+		point: Point,
+		normal: &Vector,
+		sampler: &mut Sampler,
+	) -> Result<f64, String> {
+		if self.samples == 0 {
+			return Ok(1.0)
+		}
+		let mut open = 0.0;
+		for _ in 0..self.samples {
+			let (u1, u2) = sampler.next_2d();
+			let direction = cosine_sample_hemisphere(normal, u1, u2);
+			open += world
+				.transmittance_along(point, direction, self.max_distance, None)?
+				.luminance()
+				.clamp(0.0, 1.0);
+		}
+		Ok(open / self.samples as f64)
+	}
+
+	// Visibility of the first surface hit by `ray` as a grey level, rays missing everything see
+	// white
+	pub fn value(&self, world: &World, ray: &Ray, sampler: &mut Sampler) -> Result<Color, String> {
+		let xs = world.visible_intersections(ray, true)?;
+		let visibility = match hit(&xs) {
+			Some(intersection) => {
+				let comps = ray.prepare_computations(intersection, Some(&xs));
+				self.visibility(world, comps.over_point, &comps.normal, sampler)?
+			},
+			None => 1.0,
+		};
+		Ok(Color::new(visibility, visibility, visibility))
+	}
+}
+
+impl PathTracer {
+	pub fn new(samples: usize, max_depth: usize) -> Self {
+		Self { samples, max_depth, seed: 0 }
+	}
+
+	// Monte Carlo estimate of the radiance arriving along `ray`.
+	//
+	// Each material is treated as a mix of three lobes weighted by `diffuse`, `reflective` and
+	// `transparency`. At every vertex the point light, the emissive objects and the background are
+	// sampled explicitly (next event estimation), then a single lobe is picked to continue the
+	// path: cosine weighted hemisphere sampling for the diffuse lobe, a perfect mirror for the
+	// reflective one and Fresnel weighted refraction for the transparent one.
+	pub fn radiance(
+		&self,
+		world: &World,
+		ray: &Ray,
+		sampler: &mut Sampler,
+	) -> Result<Color, String> {
+		let mut radiance = Color::new(0.0, 0.0, 0.0);
+		let mut throughput = Color::new(1.0, 1.0, 1.0);
+		let mut ray = Ray::new(ray.origin, ray.direction).with_wavelength(ray.wavelength);
+		// emitters and the background are sampled explicitly at diffuse vertices, so hitting one is
+		// only counted for camera rays and after mirror or glass bounces, unless it is an emitter
+		// which can not be sampled such as a plane
+		let mut count_emission = true;
+		// objects hidden from the camera or from bounced rays are skipped accordingly
+		let mut camera = true;
+
+		for depth in 0..self.max_depth {
+			let xs = world.visible_intersections(&ray, camera)?;
+			let intersection = match hit(&xs) {
+				Some(intersection) => intersection,
+				None => {
+					if count_emission {
+						let background = world.background.color_at(&ray.direction);
+						let background = match &world.fog {
+							Some(fog) => fog.apply(background, f64::INFINITY),
+							None => background,
+						};
+						radiance = radiance + throughput * background;
+					}
+					break
+				},
+			};
+			let comps = ray.prepare_computations(intersection, Some(&xs));
+			let speed = ray.direction.magnitude();
+			let distance = comps.t * speed;
+
+			// like emission, fog is only seen along camera rays and after mirror or glass bounces
+			if let (Some(fog), true) = (&world.fog, count_emission) {
+				let t = fog.transmittance(distance);
+				radiance = radiance + throughput * fog.color * (1.0 - t);
+				throughput = throughput * t;
+			}
+
+			// free flight sampling through a volume by delta tracking, the transmittance cancels
+			// with the pdf. Light reaching a collision is dimmed by ratio tracking up to where it
+			// enters the medium.
+			let volume = comps.medium.and_then(|m| m.material().volume.as_ref().map(|v| (m, v)));
+			if let Some((medium, volume)) = volume {
+				let towards = ray.direction / speed;
+				let collision =
+					volume.delta_tracking(medium, &ray.origin, &towards, distance, sampler);
+				if let Some(travelled) = collision {
+					let point = ray.origin + towards * travelled;
+					throughput = throughput * volume.albedo;
+					let light = world.light.spectral(ray.wavelength);
+					let transmittance =
+						world.shadow_transmittance(point, light.position, ray.wavelength)?;
+					if transmittance != Color::new(0.0, 0.0, 0.0) {
+						let to_light = light.position - point;
+						let direction = to_light.normalise();
+						let reach =
+							distance_inside(medium, &point, &direction)?.min(to_light.magnitude());
+						let shadow =
+							volume.ratio_tracking(medium, &point, &direction, reach, sampler);
+						let phase = volume.phase(&-direction, &-towards);
+						radiance = radiance +
+							throughput *
+								light.intensity_at(&point) *
+								transmittance *
+								(phase * shadow);
+					}
+					let (u1, u2) = sampler.next_2d();
+					let direction = volume.sample_phase(&towards, u1, u2);
+					ray = Ray::new(point, direction).with_wavelength(ray.wavelength);
+					count_emission = true;
+					camera = false;
+					continue
+				}
+			}
+			if comps.object.material().volume.is_some() {
+				ray = Ray::new(comps.under_point, ray.direction).with_wavelength(ray.wavelength);
+				continue
+			}
+
+			let material = comps.object.material().spectral(comps.wavelength);
+			throughput = throughput * comps.transmittance();
+
+			let shadow = if comps.object.flags().receive_shadows {
+				world.light_transmittance(comps.over_point, comps.wavelength)?
+			} else {
+				Color::new(1.0, 1.0, 1.0)
+			};
+			let direct = material.direct_lighting(
+				comps.object,
+				&world.light.spectral(comps.wavelength),
+				&comps.over_point,
+				&comps.eye,
+				&comps.normal,
+				shadow,
+			);
+			let area = world.area_lighting(&comps, sampler, 1)?;
+			let environment = world.environment_lighting(&comps, sampler, 1)?;
+			// Phong shading leaves out the 1 / PI of a diffuse BRDF, which the sampled lighting and
+			// the bounces below include
+			radiance = radiance + throughput * (direct * (1.0 / PI) + area + environment);
+			if count_emission || !comps.object.can_sample_surface() {
+				radiance = radiance + throughput * material.emissive;
+			}
+
+			let lobe = match comps.sample_lobe(&material, sampler) {
+				Some(lobe) => lobe,
+				None => break,
+			};
+			count_emission = matches!(lobe, Lobe::Specular { .. });
+			let next_ray = match lobe {
+				Lobe::Surface { weight } => {
+					let color = material.color_at(comps.object, &comps.over_point);
+					match material.sample_brdf(color, &comps.normal, &comps.eye, sampler) {
+						Some((direction, brdf)) => {
+							throughput = throughput * brdf * weight;
+							Ray::new(comps.over_point, direction).with_wavelength(comps.wavelength)
+						},
+						None => break,
+					}
+				},
+				Lobe::Specular { ray, weight } => {
+					throughput = throughput * weight;
+					ray
+				},
+			};
+
+			if depth + 1 >= RUSSIAN_ROULETTE_DEPTH {
+				let survival = throughput.red.max(throughput.green).max(throughput.blue).min(0.95);
+				if sampler.next_f64() >= survival {
+					break
+				}
+				throughput = throughput * (1.0 / survival);
+			}
+			ray = next_ray;
+			camera = false;
+		}
+		Ok(radiance)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		primitives::{point::Point, transformations::*, vector::Vector},
+		shapes::{plane::Plane, shape::ConcreteShape, spheres::Sphere},
+		visualisation::{environment::Environment, lights::PointLight, materials::Material},
+	};
+
+	#[test]
+	fn empty_world_test() {
+		let w = World::new(
+			vec![],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)),
+		);
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let mut sampler = Sampler::new(0);
+		let c = PathTracer::new(1, 5).radiance(&w, &r, &mut sampler).unwrap();
+		assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+
+		// missed rays pick up the environment
+		let w = World { background: Environment::Constant(Color::new(0.2, 0.4, 0.6)), ..w };
+		let c = PathTracer::new(1, 5).radiance(&w, &r, &mut sampler).unwrap();
+		assert_eq!(c, Color::new(0.2, 0.4, 0.6));
+	}
+
+	#[test]
+	fn direct_lighting_test() {
+		// a single plane can not light itself, so every path only picks up the direct term
+		let mut floor = Plane::default();
+		floor.get_material().color = Color::new(0.5, 0.8, 0.2);
+		let mut metal = Plane::default();
+		metal.set_material(Material::new_microfacet(Color::new(0.9, 0.6, 0.3), 1.0, 0.3));
+		for floor in [floor, metal] {
+			check_direct_lighting(floor);
+		}
+	}
+
+	fn check_direct_lighting(floor: Plane) {
+		let w = World::new(
+			vec![Box::new(floor)],
+			PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+		);
+		let r = Ray::new(Point::new(0.0, 1.0, -1.0), Vector::new(0.0, -1.0, 0.0));
+
+		let xs = r.intersect_world(&w).unwrap();
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+		let expected = w.objects[0].material().direct_lighting(
+			comps.object,
+			&w.light,
+			&comps.over_point,
+			&comps.eye,
+			&comps.normal,
+			Color::new(1.0, 1.0, 1.0),
+		) * (1.0 / PI);
+
+		let tracer = PathTracer::new(1, 5);
+		let mut sampler = Sampler::new(1);
+		for _ in 0..10 {
+			let c = tracer.radiance(&w, &r, &mut sampler).unwrap();
+			approx::assert_relative_eq!(c, expected, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn environment_lighting_test() {
+		// white diffuse floor under a white sky, lit by the sky alone
+		let mut floor = Plane::default();
+		floor.get_material().ambient = 0.0;
+		floor.get_material().diffuse = 1.0;
+		floor.get_material().specular = 0.0;
+		let mut w = World::new(
+			vec![Box::new(floor)],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+
+		let r = Ray::new(Point::new(0.0, 1.0, -1.0), Vector::new(0.0, -1.0, 1.0).normalise());
+		let tracer = PathTracer::new(1, 4);
+		let mut sampler = Sampler::new(4);
+		let n = 4000;
+		let mut sum = Color::new(0.0, 0.0, 0.0);
+		for _ in 0..n {
+			sum = sum + tracer.radiance(&w, &r, &mut sampler).unwrap();
+		}
+		approx::assert_relative_eq!(
+			sum * (1.0 / n as f64),
+			Color::new(1.0, 1.0, 1.0),
+			epsilon = 0.05
+		);
+	}
+
+	#[test]
+	fn furnace_test() {
+		// a white sphere under a white sky reflects all of it, whichever way it is lit
+		let mut s = Sphere::default();
+		s.get_material().diffuse = 1.0;
+		s.get_material().specular = 0.0;
+		let mut w = World::new(
+			vec![Box::new(s)],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+
+		let tracer = PathTracer::new(1, 8);
+		let mut sampler = Sampler::new(9);
+		let n = 4000;
+		for (origin, direction) in [
+			(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+			(Point::new(0.5, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+		] {
+			let r = Ray::new(origin, direction);
+			let mut sum = Color::new(0.0, 0.0, 0.0);
+			for _ in 0..n {
+				sum = sum + tracer.radiance(&w, &r, &mut sampler).unwrap();
+			}
+			approx::assert_relative_eq!(
+				sum * (1.0 / n as f64),
+				Color::new(1.0, 1.0, 1.0),
+				epsilon = 0.05
+			);
+		}
+
+		// a point light gives the same radiance as the BRDF times the irradiance it delivers
+		w.background = Environment::Constant(Color::new(0.0, 0.0, 0.0));
+		w.light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let c = tracer.radiance(&w, &r, &mut sampler).unwrap();
+		approx::assert_relative_eq!(c, Color::new(1.0 / PI, 1.0 / PI, 1.0 / PI), epsilon = 1e-9);
+	}
+
+	#[test]
+	fn volume_test() {
+		use crate::visualisation::medium::{Fog, Volume};
+
+		let volume_world = |albedo: Color| {
+			let mut smoke = Sphere::default();
+			smoke.set_material(Material {
+				volume: Some(Volume::new(0.5, albedo)),
+				..Material::default()
+			});
+			World::new(
+				vec![Box::new(smoke)],
+				PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)),
+			)
+		};
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let tracer = PathTracer::new(1, 16);
+		let mean = |w: &World, seed: u64| {
+			let mut sampler = Sampler::new(seed);
+			let n = 4000;
+			let mut sum = Color::new(0.0, 0.0, 0.0);
+			for _ in 0..n {
+				sum = sum + tracer.radiance(w, &r, &mut sampler).unwrap();
+			}
+			sum * (1.0 / n as f64)
+		};
+
+		// free flights through an absorbing volume leave the background dimmed by Beer's law
+		let mut w = volume_world(Color::new(0.0, 0.0, 0.0));
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+		let expected = (-1.0_f64).exp();
+		approx::assert_relative_eq!(
+			mean(&w, 5),
+			Color::new(expected, expected, expected),
+			epsilon = 0.03
+		);
+
+		// a scattering volume gathers at least the single scattered light, plus some more from
+		// further bounces
+		let w = volume_world(Color::new(1.0, 1.0, 1.0));
+		let single = w.color_at(&r, None).unwrap();
+		let traced = mean(&w, 6);
+		assert!(
+			traced.red > 0.9 * single.red && traced.red < 2.0 * single.red,
+			"{:?} {:?}",
+			traced,
+			single
+		);
+
+		// delta tracking through a density grid matches the marched transmittance
+		let grid =
+			crate::visualisation::voxel_grid::VoxelGrid::new(2, 1, 1, vec![0.2, 1.0]).unwrap();
+		let mut w = volume_world(Color::new(0.0, 0.0, 0.0));
+		w.objects[0].get_material().volume =
+			Some(Volume::new_grid(1.0, Color::new(0.0, 0.0, 0.0), grid));
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+		let off_centre = Ray::new(Point::new(0.3, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let marched = w.color_at(&off_centre, None).unwrap();
+		let mut sampler = Sampler::new(8);
+		let n = 4000;
+		let mut sum = Color::new(0.0, 0.0, 0.0);
+		for _ in 0..n {
+			sum = sum + tracer.radiance(&w, &off_centre, &mut sampler).unwrap();
+		}
+		approx::assert_relative_eq!(sum * (1.0 / n as f64), marched, epsilon = 0.03);
+
+		// fog replaces the background
+		let mut w = World::new(
+			vec![],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)),
+		);
+		w.fog = Some(Fog::new(Color::new(0.3, 0.3, 0.3), 0.1));
+		approx::assert_relative_eq!(mean(&w, 7), Color::new(0.3, 0.3, 0.3), epsilon = 1e-9);
+	}
+
+	#[test]
+	fn indirect_lighting_test() {
+		// the floor point under the sphere only receives light bounced off the ceiling
+		let floor = Plane::default();
+		let mut ceiling = Plane::default();
+		ceiling.set_transform(translation(0.0, 2.0, 0.0));
+		let mut blocker = Sphere::default();
+		blocker.set_transform(translation(0.0, 0.75, 0.0) * scaling(0.25, 0.25, 0.25));
+		let w = World::new(
+			vec![Box::new(floor), Box::new(ceiling), Box::new(blocker)],
+			PointLight::new(Point::new(0.0, 1.5, 0.0), Color::new(1.0, 1.0, 1.0)),
+		);
+
+		let r = Ray::new(Point::new(1.0, 1.0, 0.0), Vector::new(-1.0, -1.0, 0.0).normalise());
+		let xs = r.intersect_world(&w).unwrap();
+		let comps = r.prepare_computations(hit(&xs).unwrap(), Some(&xs));
+		approx::assert_relative_eq!(comps.point, Point::new(0.0, 0.0, 0.0), epsilon = 1e-9);
+		assert_eq!(
+			w.light_transmittance(comps.over_point, None).unwrap(),
+			Color::new(0.0, 0.0, 0.0)
+		);
+
+		// whitted shading only sees the ambient term here
+		let whitted = w.color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(whitted, Color::new(0.1, 0.1, 0.1));
+
+		let tracer = PathTracer::new(1, 4);
+		let mut sampler = Sampler::new(2);
+		let mut sum = Color::new(0.0, 0.0, 0.0);
+		for _ in 0..200 {
+			sum = sum + tracer.radiance(&w, &r, &mut sampler).unwrap();
+		}
+		assert!(sum.red > 0.0);
+	}
+
+	#[test]
+	fn ambient_occlusion_test() {
+		let floor = Plane::default();
+		let mut ceiling = Plane::default();
+		ceiling.set_transform(translation(0.0, 1.0, 0.0));
+		let mut w = World::new(
+			vec![Box::new(floor)],
+			PointLight::new(Point::new(0.0, 0.5, -5.0), Color::new(1.0, 1.0, 1.0)),
+		);
+		let ao = AmbientOcclusion::new(64, 2.0);
+		let point = Point::new(0.0, 1e-5, 0.0);
+		let normal = Vector::new(0.0, 1.0, 0.0);
+		let mut sampler = Sampler::new(0);
+
+		// nothing above an open floor
+		assert_eq!(ao.visibility(&w, point, &normal, &mut sampler).unwrap(), 1.0);
+
+		// a close ceiling blocks most rays, but not those escaping further than max_distance
+		w.objects.push(Box::new(ceiling));
+		let closed = ao.visibility(&w, point, &normal, &mut sampler).unwrap();
+		assert!(closed > 0.0 && closed < 0.5, "{}", closed);
+		let short = AmbientOcclusion::new(64, 0.5);
+		assert_eq!(short.visibility(&w, point, &normal, &mut sampler).unwrap(), 1.0);
+
+		// the ambient term of whitted shading is scaled by the visibility
+		let r = Ray::new(Point::new(0.0, 0.5, 0.0), Vector::new(0.0, -1.0, 0.0));
+		let lit = w.color_at(&r, None).unwrap();
+		w.ambient_occlusion = Some(ao);
+		let occluded = w.color_at(&r, None).unwrap();
+		assert!(occluded.red < lit.red);
+		approx::assert_relative_eq!(lit.red - occluded.red, 0.1 * (1.0 - closed), epsilon = 0.05);
+
+		// as a render mode the first hit is shaded by its visibility alone
+		let mut sampler = Sampler::new(0);
+		let c = ao.value(&w, &r, &mut sampler).unwrap();
+		assert_eq!(c.red, c.green);
+		assert!(c.red < 0.5);
+		let up = Ray::new(Point::new(0.0, 0.5, 0.0), Vector::new(0.0, 1.0, 0.0));
+		w.objects.pop();
+		assert_eq!(ao.value(&w, &up, &mut sampler).unwrap(), Color::new(1.0, 1.0, 1.0));
+	}
+
+	#[test]
+	fn emissive_test() {
+		let mut lamp = Sphere::default();
+		lamp.set_transform(translation(0.0, 3.0, 0.0));
+		lamp.get_material().emissive = Color::new(1.0, 1.0, 1.0);
+		let floor = Plane::default();
+		let w = World::new(
+			vec![Box::new(lamp), Box::new(floor)],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		let tracer = PathTracer::new(1, 5);
+		let mut sampler = Sampler::new(4);
+
+		// the lamp is seen directly by the camera
+		let r = Ray::new(Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let c = tracer.radiance(&w, &r, &mut sampler).unwrap();
+		assert!(c.red >= 1.0);
+
+		// the floor below is lit by it, E = PI * L * (r / d)^2
+		let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+		let n = 4000;
+		let mut sum = Color::new(0.0, 0.0, 0.0);
+		for _ in 0..n {
+			sum = sum + tracer.radiance(&w, &r, &mut sampler).unwrap();
+		}
+		// the bounce between the floor and the lamp adds a little on top of the direct light
+		let mean = sum.red / n as f64;
+		assert!(mean > 0.095 && mean < 0.15);
+	}
+
+	#[test]
+	fn emissive_plane_test() {
+		// an emissive ceiling can not be sampled as an area light, so the floor only sees it by
+		// bouncing towards it, and every bounce does
+		let mut ceiling = Plane::default();
+		ceiling.set_transform(translation(0.0, 2.0, 0.0));
+		ceiling.get_material().emissive = Color::new(1.0, 1.0, 1.0);
+		ceiling.get_material().diffuse = 0.0;
+		let w = World::new(
+			vec![Box::new(ceiling), Box::new(Plane::default())],
+			PointLight::new(Point::new(0.0, 1.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		let tracer = PathTracer::new(1, 5);
+		let mut sampler = Sampler::new(6);
+
+		let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+		for _ in 0..100 {
+			let c = tracer.radiance(&w, &r, &mut sampler).unwrap();
+			approx::assert_relative_eq!(c.red, 0.9, epsilon = 1e-9);
+		}
+	}
+
+	#[test]
+	fn glass_test() {
+		// a ray through the centre of a clear glass sphere is never scattered towards the light
+		let mut s = Sphere::new_glass_sphere();
+		s.get_material().diffuse = 0.0;
+		s.get_material().specular = 0.0;
+		let w = World::new(
+			vec![Box::new(s)],
+			PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)),
+		);
+
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let mut sampler = Sampler::new(3);
+		let c = PathTracer::new(1, 10).radiance(&w, &r, &mut sampler).unwrap();
+		assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+	}
+}
@@ -1,9 +1,19 @@
 use crate::{
-	primitives::{point::Point, vector::Vector},
+	primitives::{color::Color, point::Point, ray::Ray, sampler::Sampler, vector::Vector},
 	shapes::shape::ConcreteShape,
+	visualisation::{dispersion::CHANNEL_WAVELENGTHS, materials::Material},
 };
 
+// Part of a material a bounce continues through, `weight` makes up for picking it at random
 #[derive(Debug)]
+pub enum Lobe {
+	// diffuse or glossy surface scattering, which the caller samples itself
+	Surface { weight: f64 },
+	// mirror reflection or refraction, along `ray`
+	Specular { ray: Ray, weight: Color },
+}
+
+#[derive(Debug, Clone)]
 pub struct IntersectionComputations<'a> {
 	pub t: f64,
 	pub object: &'a dyn ConcreteShape,
@@ -16,9 +26,112 @@ pub struct IntersectionComputations<'a> {
 	pub inside: bool,
 	pub n1: f64,
 	pub n2: f64,
+	// uv carried by the intersection, shapes which do not provide one have theirs computed by
+	// `surface_uv` when needed
+	pub uv: Option<(f64, f64)>,
+	// object the ray travelled through to reach the hit, if any
+	pub medium: Option<&'a dyn ConcreteShape>,
+	// t of the boundary, of any object, crossed just before the hit, where the segment spent
+	// inside `medium` starts. 0 when no boundary lies between the ray origin and the hit.
+	pub medium_entry: f64,
+	// object the ray continues into when refracted, if any
+	pub next_medium: Option<&'a dyn ConcreteShape>,
+	// wavelength of the ray, `n1` and `n2` are taken at it
+	pub wavelength: Option<f64>,
 }
 
 impl<'a> IntersectionComputations<'a> {
+	// Light left after absorption by the medium between where the ray entered it and the hit
+	pub fn transmittance(&self) -> Color {
+		let distance = (self.t - self.medium_entry) * self.eye.magnitude();
+		match self.medium {
+			Some(medium) => medium.material().transmittance(distance),
+			None => Color::new(1.0, 1.0, 1.0),
+		}
+	}
+
+	pub fn surface_uv(&self) -> (f64, f64) {
+		self.uv.unwrap_or_else(|| self.object.uv_at(self.point))
+	}
+
+	// Whether refraction at this hit splits white light into its channels
+	pub fn is_dispersive(&self) -> bool {
+		self.wavelength.is_none() &&
+			[self.medium, self.next_medium]
+				.iter()
+				.flatten()
+				.any(|o| o.material().dispersion.is_some())
+	}
+
+	// The same hit seen by a ray of a single wavelength
+	pub fn at_wavelength(&self, wavelength: f64) -> Self {
+		let index = |medium: Option<&dyn ConcreteShape>| {
+			medium.map_or(1.0, |o| o.material().refractive_index_at(Some(wavelength)))
+		};
+		Self {
+			n1: index(self.medium),
+			n2: index(self.next_medium),
+			wavelength: Some(wavelength),
+			..self.clone()
+		}
+	}
+
+	// Picks the lobe of `material` the next bounce follows, each with probability proportional
+	// to its weight. None when the material scatters no light at all.
+	pub fn sample_lobe(&self, material: &Material, sampler: &mut Sampler) -> Option<Lobe> {
+		let surface = material.surface_weight();
+		let total = surface + material.reflective + material.transparency;
+		if total <= 0.0 {
+			return None
+		}
+
+		let u = sampler.next_f64() * total;
+		if u < surface {
+			return Some(Lobe::Surface { weight: total / surface })
+		}
+		let reflected = |comps: &Self| {
+			Ray::new(comps.over_point, comps.reflection_vector).with_wavelength(comps.wavelength)
+		};
+		if u < surface + material.reflective {
+			return Some(Lobe::Specular {
+				ray: reflected(self),
+				weight: Color::new(total, total, total),
+			})
+		}
+
+		// dispersive glass sends the bounce on with a single colour channel picked at random
+		let (comps, weight) = if self.is_dispersive() {
+			let channel = ((sampler.next_f64() * 3.0) as usize).min(2);
+			let mut mask = [0.0; 3];
+			mask[channel] = 3.0 * total;
+			(
+				self.at_wavelength(CHANNEL_WAVELENGTHS[channel]),
+				Color::new(mask[0], mask[1], mask[2]),
+			)
+		} else {
+			(self.clone(), Color::new(total, total, total))
+		};
+		let reflectance = if material.reflective > 0.0 { comps.schlick() } else { 0.0 };
+		let ray = match comps.refraction_direction() {
+			Some(direction) if sampler.next_f64() >= reflectance =>
+				Ray::new(comps.under_point, direction).with_wavelength(comps.wavelength),
+			// total internal reflection or Fresnel reflection
+			_ => reflected(&comps),
+		};
+		Some(Lobe::Specular { ray, weight })
+	}
+
+	// Schlick reflectance per colour channel, which differs between channels at dispersive hits
+	pub fn reflectance(&self) -> Color {
+		if self.is_dispersive() {
+			let [red, green, blue] = CHANNEL_WAVELENGTHS.map(|w| self.at_wavelength(w).schlick());
+			Color::new(red, green, blue)
+		} else {
+			let r = self.schlick();
+			Color::new(r, r, r)
+		}
+	}
+
 	pub fn schlick(&self) -> f64 {
 		// reference: https://graphics.stanford.edu/courses/cs148-10-summer/docs/2006--degreve--reflection_refraction.pdf
 		let mut cos = self.eye.dot(&self.normal);
@@ -33,17 +146,37 @@ impl<'a> IntersectionComputations<'a> {
 		let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
 		r0 + (1.0 - r0) * (1.0 - cos).powi(5)
 	}
+
+	// Direction of the refracted ray, None in case of total internal reflection
+	pub fn refraction_direction(&self) -> Option<Vector> {
+		// Snell's law
+		let ratio = self.n1 / self.n2;
+		let cos_i = self.eye.dot(&self.normal);
+		let sin2_t = ratio.powi(2) * (1.0 - cos_i.powi(2));
+		if sin2_t > 1.0 {
+			return None
+		}
+
+		let cos_t = (1.0 - sin2_t).sqrt();
+		Some(self.normal * (ratio * cos_i - cos_t) - self.eye * ratio)
+	}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Intersection<'a> {
 	pub t: f64,
 	pub object: &'a dyn ConcreteShape,
+	// texture coordinates known at intersection time, e.g. barycentric ones for triangles
+	pub uv: Option<(f64, f64)>,
 }
 
 impl<'a> Intersection<'a> {
 	pub fn new(t: f64, obj: &'a dyn ConcreteShape) -> Self {
-		Intersection { t, object: obj }
+		Intersection { t, object: obj, uv: None }
+	}
+
+	pub fn new_with_uv(t: f64, obj: &'a dyn ConcreteShape, u: f64, v: f64) -> Self {
+		Intersection { t, object: obj, uv: Some((u, v)) }
 	}
 }
 
@@ -110,4 +243,32 @@ mod tests {
 		s.set_transform(t);
 		assert_eq!(s.transform(), &t);
 	}
+
+	#[test]
+	fn sample_lobe_test() {
+		let s = Sphere::new(Point::new(0.0, 0.0, 0.0));
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let xs = vec![Intersection::new(4.0, &s), Intersection::new(6.0, &s)];
+		let comps = r.prepare_computations(&xs[0], Some(&xs));
+		let mut sampler = Sampler::new(0);
+
+		// materials which scatter nothing end the bounce
+		let black = Material { diffuse: 0.0, ..Material::default() };
+		assert!(comps.sample_lobe(&black, &mut sampler).is_none());
+
+		// a single lobe is always picked, weighted by the sum of all of them
+		let diffuse = Material { diffuse: 0.5, ..Material::default() };
+		match comps.sample_lobe(&diffuse, &mut sampler) {
+			Some(Lobe::Surface { weight }) => assert_eq!(weight, 1.0),
+			lobe => panic!("Expected the surface lobe, got {:?}", lobe),
+		}
+		let mirror = Material { diffuse: 0.0, reflective: 0.8, ..Material::default() };
+		match comps.sample_lobe(&mirror, &mut sampler) {
+			Some(Lobe::Specular { ray, weight }) => {
+				assert_eq!(ray.direction, comps.reflection_vector);
+				assert_eq!(weight, Color::new(0.8, 0.8, 0.8));
+			},
+			lobe => panic!("Expected the specular lobe, got {:?}", lobe),
+		}
+	}
 }
@@ -1,20 +1,87 @@
-use crate::primitives::{color::Color, point::Point};
+use crate::{
+	primitives::{color::Color, point::Point, vector::Vector},
+	visualisation::spectrum::Spectrum,
+};
+use std::borrow::Cow;
 
+// Cone restricting a light to `direction`, full strength within the `inner` half angle and
+// fading out smoothly up to the `outer` one, both in radians
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Spot {
+	pub direction: Vector,
+	pub inner: f64,
+	pub outer: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub struct PointLight {
 	pub position: Point,
 	pub intensity: Color,
+	// emission spectrum used by spectral renders, `intensity` holds its RGB colour
+	pub spectrum: Option<Spectrum>,
+	pub spot: Option<Spot>,
 }
 
 impl PointLight {
 	pub fn new(position: Point, intensity: Color) -> Self {
-		Self { position, intensity }
+		Self { position, intensity, spectrum: None, spot: None }
+	}
+
+	pub fn new_spectral(position: Point, spectrum: Spectrum) -> Self {
+		Self { position, intensity: spectrum.to_rgb(), spectrum: Some(spectrum), spot: None }
+	}
+
+	pub fn new_spot(
+		position: Point,
+		intensity: Color,
+		direction: Vector,
+		inner: f64,
+		outer: f64,
+	) -> Self {
+		Self {
+			spot: Some(Spot { direction: direction.normalise(), inner, outer }),
+			..Self::new(position, intensity)
+		}
+	}
+
+	// Intensity of the light shining on `point`
+	pub fn intensity_at(&self, point: &Point) -> Color {
+		let spot = match &self.spot {
+			Some(spot) => spot,
+			None => return self.intensity,
+		};
+		let cos = (*point - self.position).normalise().dot(&spot.direction);
+		let (cos_inner, cos_outer) = (spot.inner.cos(), spot.outer.cos());
+		if cos >= cos_inner {
+			return self.intensity
+		}
+		if cos <= cos_outer {
+			return Color::new(0.0, 0.0, 0.0)
+		}
+		let t = (cos - cos_outer) / (cos_inner - cos_outer);
+		self.intensity * (t * t * (3.0 - 2.0 * t))
+	}
+
+	// The light seen by a ray of a single wavelength, its spectrum becomes a grey intensity
+	pub fn spectral(&self, wavelength: Option<f64>) -> Cow<'_, Self> {
+		match (&self.spectrum, wavelength) {
+			(Some(spectrum), Some(wavelength)) => {
+				let value = spectrum.value(wavelength);
+				Cow::Owned(Self {
+					intensity: Color::new(value, value, value),
+					spectrum: None,
+					..self.clone()
+				})
+			},
+			_ => Cow::Borrowed(self),
+		}
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::f64::consts::PI;
 
 	#[test]
 	fn initialisation() {
@@ -25,4 +92,43 @@ mod tests {
 		assert_eq!(light.position, position);
 		assert_eq!(light.intensity, intensity);
 	}
+
+	#[test]
+	fn spot_test() {
+		let light = PointLight::new_spot(
+			Point::new(0.0, 10.0, 0.0),
+			Color::new(1.0, 1.0, 1.0),
+			Vector::new(0.0, -2.0, 0.0),
+			PI / 8.0,
+			PI / 4.0,
+		);
+		let below = |angle: f64| Point::new(10.0 * angle.tan(), 0.0, 0.0);
+		assert_eq!(light.intensity_at(&below(0.0)), light.intensity);
+		assert_eq!(light.intensity_at(&below(PI / 10.0)), light.intensity);
+		assert_eq!(light.intensity_at(&below(PI / 3.0)), Color::new(0.0, 0.0, 0.0));
+		assert_eq!(light.intensity_at(&Point::new(0.0, 20.0, 0.0)), Color::new(0.0, 0.0, 0.0));
+
+		// smooth fall off between the two cones
+		let edge = light.intensity_at(&below(3.0 * PI / 16.0));
+		assert!(edge.red > 0.0 && edge.red < 1.0);
+		assert!(light.intensity_at(&below(0.7)).red < edge.red);
+
+		let plain = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
+		assert_eq!(plain.intensity_at(&Point::new(0.0, 20.0, 0.0)), plain.intensity);
+	}
+
+	#[test]
+	fn spectral_light_test() {
+		let position = Point::new(1.0, 1.0, 1.0);
+		let light = PointLight::new_spectral(position, Spectrum::new_blackbody(3000.0));
+		assert_eq!(light.intensity, Spectrum::new_blackbody(3000.0).to_rgb());
+
+		let value = Spectrum::new_blackbody(3000.0).value(500.0);
+		assert_eq!(light.spectral(Some(500.0)).intensity, Color::new(value, value, value));
+		assert_eq!(*light.spectral(None), light);
+
+		// plain lights are left alone, spectral renders project their colour instead
+		let plain = PointLight::new(position, Color::new(1.0, 0.5, 0.0));
+		assert_eq!(*plain.spectral(Some(500.0)), plain);
+	}
 }
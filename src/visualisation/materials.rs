@@ -1,12 +1,30 @@
 use crate::{
 	patterns::color_pattern::ColorPattern,
-	primitives::{color::Color, point::Point, vector::Vector},
+	primitives::{
+		color::Color,
+		point::Point,
+		sampler::{cosine_sample_hemisphere, Sampler},
+		vector::Vector,
+	},
 	shapes::shape::ConcreteShape,
-	visualisation::lights::PointLight,
+	visualisation::{
+		bump::Bump, dispersion::Dispersion, lights::PointLight, medium::Volume,
+		microfacet::Microfacet, spectrum::Spectrum,
+	},
 };
 use approx::{AbsDiffEq, RelativeEq};
+use std::{borrow::Cow, f64::consts::PI};
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Default)]
+pub enum ShadingModel {
+	// ad-hoc `ambient`, `diffuse`, `specular` and `shininess` parameters
+	#[default]
+	Phong,
+	// physically based metallic/roughness model, only `ambient` is shared with Phong
+	Microfacet(Microfacet),
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct Material {
 	pub pattern: Option<ColorPattern>,
 	pub color: Color,
@@ -17,6 +35,25 @@ pub struct Material {
 	pub shininess: f64,
 	pub transparency: f64,
 	pub refractive_index: f64,
+	// light emitted by the surface itself, the colour is scaled by the strength of the emitter
+	pub emissive: Color,
+	// Beer-Lambert absorption of light travelling through a transparent object, per unit distance
+	// and colour channel, scaled by `absorption_density`
+	pub absorption: Color,
+	pub absorption_density: f64,
+	// wavelength dependent refractive index, replaces `refractive_index` for rays split into
+	// single wavelengths
+	pub dispersion: Option<Dispersion>,
+	// reflectance spectrum used by spectral renders, `color` holds its RGB colour
+	pub spectrum: Option<Spectrum>,
+	// fills the inside of the object with a participating medium, the surface itself is invisible
+	pub volume: Option<Volume>,
+	pub model: ShadingModel,
+	// perturbs the shading normal, see `Ray::prepare_computations`
+	pub bump: Option<Bump>,
+	// objects whose materials carry the same id share it in the material ID AOV, objects without
+	// one get an id of their own
+	pub id: Option<usize>,
 }
 
 impl Material {
@@ -41,6 +78,26 @@ impl Material {
 			shininess,
 			transparency,
 			refractive_index,
+			emissive: Color::new(0.0, 0.0, 0.0),
+			absorption: Color::new(0.0, 0.0, 0.0),
+			absorption_density: 1.0,
+			dispersion: None,
+			spectrum: None,
+			volume: None,
+			model: ShadingModel::Phong,
+			bump: None,
+			id: None,
+		}
+	}
+
+	// Physically based material, the refractive index of common dielectrics gives them the
+	// usual 4% reflectance at normal incidence
+	pub fn new_microfacet(color: Color, metallic: f64, roughness: f64) -> Self {
+		Self {
+			color,
+			refractive_index: 1.5,
+			model: ShadingModel::Microfacet(Microfacet::new(metallic, roughness)),
+			..Self::default()
 		}
 	}
 
@@ -51,31 +108,157 @@ impl Material {
 		point: &Point,
 		eye: &Vector,
 		normal: &Vector,
-		in_shadow: bool,
+		shadow: Color,
 	) -> Color {
-		let mut color = self.color;
-		if let Some(pattern) = self.pattern {
-			color = pattern.pattern_at_object(object, point);
+		self.ambient_lighting(object, light, point) +
+			self.direct_lighting(object, light, point, eye, normal, shadow)
+	}
+
+	// Ambient term of lighting, scaled by `AmbientOcclusion` visibility when enabled on the world
+	pub fn ambient_lighting(
+		&self,
+		object: &dyn ConcreteShape,
+		light: &PointLight,
+		point: &Point,
+	) -> Color {
+		self.color_at(object, point) * light.intensity * self.ambient
+	}
+
+	// Light arriving straight from the light source, i.e. lighting without the ambient term.
+	// `shadow` is the share of the light which gets past the objects in between, see
+	// `World::shadow_transmittance`.
+	pub fn direct_lighting(
+		&self,
+		object: &dyn ConcreteShape,
+		light: &PointLight,
+		point: &Point,
+		eye: &Vector,
+		normal: &Vector,
+		shadow: Color,
+	) -> Color {
+		let (diffuse, specular) = self.surface(object, light, point, eye, normal, shadow);
+		diffuse + specular
+	}
+
+	pub fn is_emissive(&self) -> bool {
+		self.emissive != Color::new(0.0, 0.0, 0.0)
+	}
+
+	pub fn new_spectral(spectrum: Spectrum) -> Self {
+		Self { color: spectrum.to_rgb(), spectrum: Some(spectrum), ..Self::default() }
+	}
+
+	// The material seen by a ray of a single wavelength, its spectrum becomes a grey colour.
+	// Materials without a spectrum are left alone and their colours projected on the film instead.
+	pub fn spectral(&self, wavelength: Option<f64>) -> Cow<'_, Self> {
+		match (&self.spectrum, wavelength) {
+			(Some(spectrum), Some(wavelength)) => {
+				let value = spectrum.value(wavelength);
+				Cow::Owned(Self {
+					pattern: None,
+					color: Color::new(value, value, value),
+					spectrum: None,
+					..self.clone()
+				})
+			},
+			_ => Cow::Borrowed(self),
+		}
+	}
+
+	pub fn refractive_index_at(&self, wavelength: Option<f64>) -> f64 {
+		match (self.dispersion, wavelength) {
+			(Some(dispersion), Some(wavelength)) => dispersion.index(wavelength),
+			_ => self.refractive_index,
+		}
+	}
+
+	// Fraction of the light left after travelling `distance` inside the object
+	pub fn transmittance(&self, distance: f64) -> Color {
+		let a = self.absorption * (self.absorption_density * distance);
+		Color::new((-a.red).exp(), (-a.green).exp(), (-a.blue).exp())
+	}
+
+	pub fn color_at(&self, object: &dyn ConcreteShape, point: &Point) -> Color {
+		match &self.pattern {
+			Some(pattern) => pattern.pattern_at_object(object, point),
+			None => self.color,
 		}
+	}
+
+	// Weight of the surface scattering compared to the `reflective` and `transparency` lobes
+	pub fn surface_weight(&self) -> f64 {
+		match self.model {
+			ShadingModel::Phong => self.diffuse,
+			ShadingModel::Microfacet(_) => 1.0,
+		}
+	}
+
+	// BRDF of the surface for light arriving from `light_dir`. The Phong specular highlight is a
+	// point light only effect, so Phong materials are purely diffuse here.
+	pub fn brdf(&self, color: Color, normal: &Vector, eye: &Vector, light_dir: &Vector) -> Color {
+		match self.model {
+			ShadingModel::Phong => color * (self.diffuse / PI),
+			ShadingModel::Microfacet(m) =>
+				m.evaluate(color, self.refractive_index, normal, eye, light_dir),
+		}
+	}
 
-		let effective_color = color * light.intensity;
+	// Samples a direction of incoming light for the surface lobe, returns it together with
+	// brdf * cos / pdf
+	pub fn sample_brdf(
+		&self,
+		color: Color,
+		normal: &Vector,
+		eye: &Vector,
+		sampler: &mut Sampler,
+	) -> Option<(Vector, Color)> {
+		match self.model {
+			ShadingModel::Phong => {
+				let (u1, u2) = sampler.next_2d();
+				Some((cosine_sample_hemisphere(normal, u1, u2), color * self.diffuse))
+			},
+			ShadingModel::Microfacet(m) => {
+				let u = (sampler.next_f64(), sampler.next_f64(), sampler.next_f64());
+				m.sample(color, self.refractive_index, normal, eye, u)
+			},
+		}
+	}
+
+	// Diffuse and specular light from a point light. Point lights follow the Phong convention and
+	// are not divided by PI, which keeps both models equally bright.
+	fn surface(
+		&self,
+		object: &dyn ConcreteShape,
+		light: &PointLight,
+		point: &Point,
+		eye: &Vector,
+		normal: &Vector,
+		shadow: Color,
+	) -> (Color, Color) {
+		let color = self.color_at(object, point);
+		let intensity = light.intensity_at(point) * shadow;
+		let effective_color = color * intensity;
 		let light_dir = (light.position - *point).normalise();
 
-		let ambient = effective_color * self.ambient;
 		let mut diffuse = Color::new(0.0, 0.0, 0.0);
 		let mut specular = Color::new(0.0, 0.0, 0.0);
 
-		let light_dot_normal = light_dir.dot(&normal);
-		if light_dot_normal >= 0.0 && !in_shadow {
+		let light_dot_normal = light_dir.dot(normal);
+		if let ShadingModel::Microfacet(m) = self.model {
+			if light_dot_normal > 0.0 {
+				let f = m.evaluate(color, self.refractive_index, normal, eye, &light_dir);
+				diffuse = f * intensity * (light_dot_normal * PI);
+			}
+		} else if light_dot_normal >= 0.0 {
 			diffuse = effective_color * self.diffuse * light_dot_normal;
 			let reflect_dir = -light_dir.reflect(*normal);
 			let reflect_dot_eye = reflect_dir.dot(eye);
 			if reflect_dot_eye > 0.0 {
 				let factor = reflect_dot_eye.powf(self.shininess);
-				specular = light.intensity * self.specular * factor;
+				specular = intensity * self.specular * factor;
 			}
 		}
-		ambient + diffuse + specular
+		(diffuse, specular)
 	}
 }
 
@@ -91,6 +274,15 @@ impl Default for Material {
 			shininess: 200.0,
 			transparency: 0.0,
 			refractive_index: 1.0,
+			emissive: Color::new(0.0, 0.0, 0.0),
+			absorption: Color::new(0.0, 0.0, 0.0),
+			absorption_density: 1.0,
+			dispersion: None,
+			spectrum: None,
+			volume: None,
+			model: ShadingModel::Phong,
+			bump: None,
+			id: None,
 		}
 	}
 }
@@ -142,20 +334,22 @@ mod tests {
 		let s = Sphere::default();
 		let m = Material::default();
 		let position = Point::new(0.0, 0.0, 0.0);
+		let lit = Color::new(1.0, 1.0, 1.0);
+		let shadowed = Color::new(0.0, 0.0, 0.0);
 
 		// Lighting with the eye between the light and the surface
 		let eye = Vector::new(0.0, 0.0, -1.0);
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-		let result = m.lighting(&s, &light, &position, &eye, &n, false);
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
 		approx::assert_relative_eq!(result, Color::new(1.9, 1.9, 1.9));
 
 		// Lighting with the eye between the light and the surface, eye offset 45 deg
 		let eye = Vector::new(0.0, 2.0f64.sqrt() / 2.0, -2.0f64.sqrt() / 2.0);
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
-		let result = m.lighting(&s, &light, &position, &eye, &n, false);
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
 		approx::assert_relative_eq!(result, Color::new(1.0, 1.0, 1.0));
 
 		// Lighting with the surface in shadow
@@ -163,15 +357,19 @@ mod tests {
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-		let result = m.lighting(&s, &light, &position, &eye, &n, true);
+		let result = m.lighting(&s, &light, &position, &eye, &n, shadowed);
 		approx::assert_relative_eq!(result, Color::new(0.1, 0.1, 0.1));
 
+		// Lighting through a tinted transparent occluder
+		let result = m.lighting(&s, &light, &position, &eye, &n, Color::new(0.5, 1.0, 0.0));
+		approx::assert_relative_eq!(result, Color::new(1.0, 1.9, 0.1));
+
 		// Lighting with eye opposite surface
 		let eye = Vector::new(0.0, 0.0, -1.0);
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-		let result = m.lighting(&s, &light, &position, &eye, &n, false);
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
 		approx::assert_relative_eq!(
 			result,
 			Color::new(0.736396, 0.736396, 0.736396),
@@ -183,7 +381,7 @@ mod tests {
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
 
-		let result = m.lighting(&s, &light, &position, &eye, &n, false);
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
 		approx::assert_relative_eq!(
 			result,
 			Color::new(1.6363961030678928, 1.6363961030678928, 1.6363961030678928)
@@ -194,7 +392,7 @@ mod tests {
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
 
-		let result = m.lighting(&s, &light, &position, &eye, &n, false);
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
 		approx::assert_relative_eq!(result, Color::new(0.1, 0.1, 0.1));
 
 		// Lighting with pattern applied
@@ -207,13 +405,42 @@ mod tests {
 		let eye = Vector::new(0.0, 0.0, -1.0);
 		let n = Vector::new(0.0, 0.0, -1.0);
 		let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
-		let c1 = m1.lighting(&s, &light, &Point::new(0.9, 0.0, 0.0), &eye, &n, false);
-		let c2 = m1.lighting(&s, &light, &Point::new(1.1, 0.0, 0.0), &eye, &n, false);
+		let c1 = m1.lighting(&s, &light, &Point::new(0.9, 0.0, 0.0), &eye, &n, lit);
+		let c2 = m1.lighting(&s, &light, &Point::new(1.1, 0.0, 0.0), &eye, &n, lit);
 
 		approx::assert_relative_eq!(c1, Color::new(1.0, 1.0, 1.0));
 		approx::assert_relative_eq!(c2, Color::new(0.0, 0.0, 0.0));
 	}
 
+	#[test]
+	fn microfacet_lighting_test() {
+		let s = Sphere::default();
+		let m = Material::new_microfacet(Color::new(1.0, 1.0, 1.0), 0.0, 0.5);
+		let position = Point::new(0.0, 0.0, 0.0);
+		let eye = Vector::new(0.0, 0.0, -1.0);
+		let n = Vector::new(0.0, 0.0, -1.0);
+		let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+		let lit = Color::new(1.0, 1.0, 1.0);
+		let shadowed = Color::new(0.0, 0.0, 0.0);
+
+		// ambient plus PI * brdf for light and eye along the normal
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
+		let f = m.brdf(m.color, &n, &eye, &eye);
+		approx::assert_relative_eq!(result, Color::new(0.1, 0.1, 0.1) + f * PI, epsilon = 1e-9);
+		approx::assert_relative_eq!(
+			f.red,
+			5.092958178940651 * 0.01 + 0.96 * 0.96 / PI,
+			epsilon = 1e-9
+		);
+
+		// only ambient light in shadow or with the light behind the surface
+		let result = m.lighting(&s, &light, &position, &eye, &n, shadowed);
+		approx::assert_relative_eq!(result, Color::new(0.1, 0.1, 0.1));
+		let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
+		let result = m.lighting(&s, &light, &position, &eye, &n, lit);
+		approx::assert_relative_eq!(result, Color::new(0.1, 0.1, 0.1));
+	}
+
 	#[test]
 	fn test_reflection_vector() {
 		let p = Plane::default();
@@ -0,0 +1,261 @@
+use crate::{
+	primitives::{
+		color::Color,
+		point::Point,
+		ray::Ray,
+		sampler::{orthonormal_basis, Sampler},
+		vector::Vector,
+	},
+	shapes::shape::ConcreteShape,
+	visualisation::voxel_grid::VoxelGrid,
+};
+use std::{f64::consts::PI, sync::Arc};
+
+// Global exponential fog. Over a distance `d` only exp(-density * d) of the light survives and
+// the rest is replaced by the fog colour, so misses fade entirely into it.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Fog {
+	pub color: Color,
+	pub density: f64,
+}
+
+impl Fog {
+	pub fn new(color: Color, density: f64) -> Self {
+		Self { color, density }
+	}
+
+	pub fn transmittance(&self, distance: f64) -> f64 {
+		(-self.density * distance).exp()
+	}
+
+	// `color` seen through `distance` units of fog
+	pub fn apply(&self, color: Color, distance: f64) -> Color {
+		let t = self.transmittance(distance);
+		color * t + self.color * (1.0 - t)
+	}
+}
+
+// Participating medium filling the inside of a shape. `density` is the extinction coefficient per
+// unit distance and `albedo` the fraction of it which is scattered rather than absorbed.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Volume {
+	pub density: f64,
+	pub albedo: Color,
+	// Henyey-Greenstein asymmetry, positive values scatter forwards, 0 is isotropic
+	pub anisotropy: f64,
+	// scales `density` across the object space cube from -1 to 1, the medium is homogeneous
+	// without it
+	pub grid: Option<Arc<VoxelGrid>>,
+}
+
+impl Volume {
+	pub fn new(density: f64, albedo: Color) -> Self {
+		Self { density, albedo, anisotropy: 0.0, grid: None }
+	}
+
+	pub fn new_grid(density: f64, albedo: Color, grid: VoxelGrid) -> Self {
+		Self { grid: Some(Arc::new(grid)), ..Self::new(density, albedo) }
+	}
+
+	// Transmittance over `distance` of the homogeneous medium
+	pub fn transmittance(&self, distance: f64) -> f64 {
+		(-self.density * distance).exp()
+	}
+
+	// Extinction coefficient at a world space `point` inside `object`
+	pub fn density_at(&self, object: &dyn ConcreteShape, point: &Point) -> f64 {
+		match &self.grid {
+			Some(_) => self.density_function(object)(point),
+			None => self.density,
+		}
+	}
+
+	// Largest density anywhere in the medium, bounds the tracking estimators
+	pub fn majorant(&self) -> f64 {
+		match &self.grid {
+			Some(grid) => self.density * grid.max(),
+			None => self.density,
+		}
+	}
+
+	// World space density lookup with the object transform inverted once
+	fn density_function<'a>(&'a self, object: &dyn ConcreteShape) -> impl Fn(&Point) -> f64 + 'a {
+		let inverse = object.transform().inverse().expect("Cannot invert volume transform");
+		move |point: &Point| match &self.grid {
+			Some(grid) => {
+				let p = (inverse * *point).tuple;
+				self.density * grid.sample((p.x + 1.0) / 2.0, (p.y + 1.0) / 2.0, (p.z + 1.0) / 2.0)
+			},
+			None => self.density,
+		}
+	}
+
+	// Integral of the density over `distance` along the unit `direction`, estimated at the
+	// midpoints of `steps` segments. Exact for homogeneous media.
+	pub fn optical_depth(
+		&self,
+		object: &dyn ConcreteShape,
+		origin: &Point,
+		direction: &Vector,
+		distance: f64,
+		steps: usize,
+	) -> f64 {
+		if self.grid.is_none() {
+			return self.density * distance
+		}
+		let density = self.density_function(object);
+		let step = distance / steps as f64;
+		(0..steps)
+			.map(|i| density(&(*origin + *direction * ((i as f64 + 0.5) * step))) * step)
+			.sum()
+	}
+
+	// Delta tracking: distance along the unit `direction` of the first real collision within
+	// `distance`, sampled proportionally to density times transmittance. `None` when the light
+	// passes through.
+	pub fn delta_tracking(
+		&self,
+		object: &dyn ConcreteShape,
+		origin: &Point,
+		direction: &Vector,
+		distance: f64,
+		sampler: &mut Sampler,
+	) -> Option<f64> {
+		let majorant = self.majorant();
+		if majorant <= 0.0 {
+			return None
+		}
+		let density = self.density_function(object);
+		let mut t = 0.0;
+		loop {
+			t -= (1.0 - sampler.next_f64()).ln() / majorant;
+			if t >= distance {
+				return None
+			}
+			// homogeneous media never have null collisions
+			if self.grid.is_none() ||
+				sampler.next_f64() * majorant < density(&(*origin + *direction * t))
+			{
+				return Some(t)
+			}
+		}
+	}
+
+	// Ratio tracking: unbiased estimate of the transmittance over `distance` along the unit
+	// `direction`, exact for homogeneous media
+	pub fn ratio_tracking(
+		&self,
+		object: &dyn ConcreteShape,
+		origin: &Point,
+		direction: &Vector,
+		distance: f64,
+		sampler: &mut Sampler,
+	) -> f64 {
+		let majorant = self.majorant();
+		if self.grid.is_none() || majorant <= 0.0 {
+			return self.transmittance(distance)
+		}
+		let density = self.density_function(object);
+		let (mut t, mut transmittance) = (0.0, 1.0);
+		loop {
+			t -= (1.0 - sampler.next_f64()).ln() / majorant;
+			if t >= distance {
+				return transmittance
+			}
+			transmittance *= 1.0 - density(&(*origin + *direction * t)) / majorant;
+		}
+	}
+
+	// Density of light travelling along `incoming` being scattered into `outgoing`, both unit
+	// vectors
+	pub fn phase(&self, incoming: &Vector, outgoing: &Vector) -> f64 {
+		henyey_greenstein(incoming.dot(outgoing), self.anisotropy)
+	}
+
+	// Samples an outgoing direction for light travelling along `incoming` proportionally to the
+	// phase function, which therefore cancels out of the path weight
+	pub fn sample_phase(&self, incoming: &Vector, u1: f64, u2: f64) -> Vector {
+		let g = self.anisotropy;
+		let cos_theta = if g.abs() < 1e-3 {
+			1.0 - 2.0 * u1
+		} else {
+			let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+			((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+		};
+		let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+		let phi = 2.0 * PI * u2;
+		let (t, b) = orthonormal_basis(incoming);
+		t * (sin_theta * phi.cos()) + b * (sin_theta * phi.sin()) + *incoming * cos_theta
+	}
+}
+
+// Distance along `direction` from a `point` inside `object` to where it leaves the object
+pub fn distance_inside(
+	object: &dyn ConcreteShape,
+	point: &Point,
+	direction: &Vector,
+) -> Result<f64, String> {
+	let xs = object.intersects(&Ray::new(*point, *direction))?;
+	let t = xs.iter().map(|i| i.t).filter(|t| *t > 0.0).fold(f64::INFINITY, f64::min);
+	Ok(t * direction.magnitude())
+}
+
+// Henyey-Greenstein phase function for the angle between the directions light travels before
+// and after scattering, normalised over the sphere
+pub fn henyey_greenstein(cos_theta: f64, g: f64) -> f64 {
+	let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+	(1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::sampler::Sampler;
+
+	#[test]
+	fn fog_test() {
+		let fog = Fog::new(Color::new(0.5, 0.5, 0.5), 0.5);
+		let red = Color::new(1.0, 0.0, 0.0);
+		assert_eq!(fog.apply(red, 0.0), red);
+		let t = (-1.0_f64).exp();
+		approx::assert_relative_eq!(
+			fog.apply(red, 2.0),
+			Color::new(t + 0.5 * (1.0 - t), 0.5 * (1.0 - t), 0.5 * (1.0 - t))
+		);
+		approx::assert_relative_eq!(fog.apply(red, f64::INFINITY), fog.color);
+	}
+
+	#[test]
+	fn phase_test() {
+		approx::assert_relative_eq!(henyey_greenstein(0.3, 0.0), 1.0 / (4.0 * PI));
+
+		// normalised over the sphere and peaked forwards for positive asymmetry
+		for g in [-0.5, 0.0, 0.3, 0.8] {
+			let steps = 10000;
+			let mut integral = 0.0;
+			for i in 0..steps {
+				let cos = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+				integral += henyey_greenstein(cos, g) * 2.0 * PI * 2.0 / steps as f64;
+			}
+			approx::assert_relative_eq!(integral, 1.0, epsilon = 1e-3);
+		}
+		assert!(henyey_greenstein(1.0, 0.5) > henyey_greenstein(-1.0, 0.5));
+
+		// sampled directions are unit vectors whose mean cosine is the asymmetry
+		let incoming = Vector::new(0.0, 0.6, 0.8);
+		let mut sampler = Sampler::new(3);
+		for g in [0.0, 0.6, -0.4] {
+			let mut volume = Volume::new(1.0, Color::new(1.0, 1.0, 1.0));
+			volume.anisotropy = g;
+			let samples = 20000;
+			let mut mean = 0.0;
+			for _ in 0..samples {
+				let (u1, u2) = sampler.next_2d();
+				let outgoing = volume.sample_phase(&incoming, u1, u2);
+				approx::assert_relative_eq!(outgoing.magnitude(), 1.0, epsilon = 1e-9);
+				mean += outgoing.dot(&incoming) / samples as f64;
+			}
+			approx::assert_relative_eq!(mean, g, epsilon = 0.02);
+		}
+	}
+}
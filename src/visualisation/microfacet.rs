@@ -0,0 +1,218 @@
+use crate::primitives::{color::Color, sampler::orthonormal_basis, vector::Vector};
+use std::f64::consts::PI;
+
+// roughness is squared into the GGX alpha, clamp it so perfectly smooth surfaces stay finite
+const MIN_ALPHA: f64 = 1e-4;
+
+// Metallic/roughness microfacet model.
+//
+// The specular lobe is a Cook-Torrance BRDF with the GGX normal distribution, the separable
+// Smith shadowing-masking term and Schlick's Fresnel approximation. The normal incidence
+// reflectance of dielectrics comes from the material `refractive_index`, metals use the base
+// colour instead. Only light which gets through the Fresnel term on the way in and on the way out
+// is scattered diffusely (dielectrics only), so the model never reflects more energy than it
+// receives.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Microfacet {
+	pub metallic: f64,
+	pub roughness: f64,
+}
+
+impl Microfacet {
+	pub fn new(metallic: f64, roughness: f64) -> Self {
+		Self { metallic, roughness }
+	}
+
+	pub fn alpha(&self) -> f64 {
+		(self.roughness * self.roughness).max(MIN_ALPHA)
+	}
+
+	// GGX / Trowbridge-Reitz normal distribution
+	pub fn distribution(&self, n_dot_h: f64) -> f64 {
+		let a2 = self.alpha().powi(2);
+		let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+		a2 / (PI * d * d)
+	}
+
+	// Smith shadowing-masking as a product of the two one sided terms
+	pub fn geometry(&self, n_dot_v: f64, n_dot_l: f64) -> f64 {
+		let a2 = self.alpha().powi(2);
+		let g1 = |cos: f64| 2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt());
+		g1(n_dot_v) * g1(n_dot_l)
+	}
+
+	// Fresnel-Schlick reflectance
+	pub fn fresnel(&self, base_color: Color, refractive_index: f64, v_dot_h: f64) -> Color {
+		let f0 = ((refractive_index - 1.0) / (refractive_index + 1.0)).powi(2);
+		let f0 = Color::new(f0, f0, f0) * (1.0 - self.metallic) + base_color * self.metallic;
+		let white = Color::new(1.0, 1.0, 1.0);
+		f0 + (white - f0) * (1.0 - v_dot_h).max(0.0).powi(5)
+	}
+
+	// BRDF value for light arriving from `light_dir` and leaving towards `eye`
+	pub fn evaluate(
+		&self,
+		base_color: Color,
+		refractive_index: f64,
+		normal: &Vector,
+		eye: &Vector,
+		light_dir: &Vector,
+	) -> Color {
+		let n_dot_l = normal.dot(light_dir);
+		let n_dot_v = normal.dot(eye);
+		if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+			return Color::new(0.0, 0.0, 0.0)
+		}
+
+		let half = (*eye + *light_dir).normalise();
+		let fresnel = self.fresnel(base_color, refractive_index, eye.dot(&half));
+		let specular = fresnel *
+			(self.distribution(normal.dot(&half)) * self.geometry(n_dot_v, n_dot_l) /
+				(4.0 * n_dot_l * n_dot_v));
+
+		// light refracted into the surface crosses it twice before leaving diffusely
+		let white = Color::new(1.0, 1.0, 1.0);
+		let transmitted = (white - self.fresnel(base_color, refractive_index, n_dot_v)) *
+			(white - self.fresnel(base_color, refractive_index, n_dot_l));
+		let diffuse = transmitted * base_color * ((1.0 - self.metallic) / PI);
+		specular + diffuse
+	}
+
+	// probability of sampling the specular lobe rather than the diffuse one
+	fn specular_probability(&self) -> f64 {
+		0.5 * (1.0 + self.metallic)
+	}
+
+	// Density of `sample` picking `light_dir`, with respect to solid angle
+	pub fn pdf(&self, normal: &Vector, eye: &Vector, light_dir: &Vector) -> f64 {
+		let n_dot_l = normal.dot(light_dir);
+		if n_dot_l <= 0.0 {
+			return 0.0
+		}
+		let half = (*eye + *light_dir).normalise();
+		let specular =
+			self.distribution(normal.dot(&half)) * normal.dot(&half) / (4.0 * eye.dot(&half));
+		let diffuse = n_dot_l / PI;
+		let p = self.specular_probability();
+		p * specular + (1.0 - p) * diffuse
+	}
+
+	// Picks an incoming light direction, either through GGX half vector sampling or cosine
+	// weighted sampling, and returns it with brdf * cos / pdf.
+	pub fn sample(
+		&self,
+		base_color: Color,
+		refractive_index: f64,
+		normal: &Vector,
+		eye: &Vector,
+		u: (f64, f64, f64),
+	) -> Option<(Vector, Color)> {
+		let (tangent, bitangent) = orthonormal_basis(normal);
+		let phi = 2.0 * PI * u.2;
+		let light_dir = if u.0 < self.specular_probability() {
+			let u1 = u.1;
+			let cos2 = (1.0 - u1) / (1.0 + (self.alpha().powi(2) - 1.0) * u1);
+			let (cos, sin) = (cos2.sqrt(), (1.0 - cos2).max(0.0).sqrt());
+			let half = tangent * (sin * phi.cos()) + bitangent * (sin * phi.sin()) + *normal * cos;
+			-eye.reflect(half)
+		} else {
+			let r = u.1.sqrt();
+			let z = (1.0 - u.1).max(0.0).sqrt();
+			(tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + *normal * z).normalise()
+		};
+
+		let pdf = self.pdf(normal, eye, &light_dir);
+		if pdf <= 0.0 {
+			return None
+		}
+		let f = self.evaluate(base_color, refractive_index, normal, eye, &light_dir);
+		Some((light_dir, f * (normal.dot(&light_dir) / pdf)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::primitives::sampler::Sampler;
+
+	#[test]
+	fn distribution_test() {
+		// at normal incidence D = 1 / (PI * alpha^2)
+		let m = Microfacet::new(0.0, 0.5);
+		approx::assert_relative_eq!(m.distribution(1.0), 5.092958178940651);
+		approx::assert_relative_eq!(m.geometry(1.0, 1.0), 1.0);
+	}
+
+	#[test]
+	fn fresnel_test() {
+		let m = Microfacet::new(0.0, 0.5);
+		let white = Color::new(1.0, 1.0, 1.0);
+		// glass reflects 4% at normal incidence and everything at grazing angles
+		approx::assert_relative_eq!(m.fresnel(white, 1.5, 1.0), Color::new(0.04, 0.04, 0.04));
+		approx::assert_relative_eq!(m.fresnel(white, 1.5, 0.0), white);
+
+		// metals reflect their base colour
+		let m = Microfacet::new(1.0, 0.5);
+		let gold = Color::new(1.0, 0.78, 0.34);
+		approx::assert_relative_eq!(m.fresnel(gold, 1.5, 1.0), gold);
+	}
+
+	#[test]
+	fn evaluate_test() {
+		let n = Vector::new(0.0, 1.0, 0.0);
+
+		// eye and light along the normal
+		let m = Microfacet::new(0.0, 0.5);
+		let white = Color::new(1.0, 1.0, 1.0);
+		let f = m.evaluate(white, 1.5, &n, &n, &n);
+		let expected = 5.092958178940651 * 0.04 / 4.0 + 0.96 * 0.96 / PI;
+		approx::assert_relative_eq!(f, Color::new(expected, expected, expected), epsilon = 1e-9);
+
+		// oblique dielectric
+		let eye = Vector::new(0.0, 1.0, -1.0).normalise();
+		let light = Vector::new(1.0, 2.0, 0.5).normalise();
+		let f = m.evaluate(white, 1.5, &n, &eye, &light);
+		approx::assert_relative_eq!(
+			f,
+			Color::new(0.3006127097, 0.3006127097, 0.3006127097),
+			epsilon = 1e-9
+		);
+
+		// oblique half metal
+		let m = Microfacet::new(0.5, 0.3);
+		let f = m.evaluate(Color::new(0.9, 0.6, 0.2), 1.5, &n, &eye, &light);
+		approx::assert_relative_eq!(
+			f,
+			Color::new(0.0608707672, 0.0581693870, 0.0298889993),
+			epsilon = 1e-9
+		);
+
+		// light below the surface
+		let f = m.evaluate(white, 1.5, &n, &eye, &-light);
+		assert_eq!(f, Color::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn energy_conservation_test() {
+		// white furnace: the reflected fraction of uniform incoming light never exceeds one
+		let n = Vector::new(0.0, 1.0, 0.0);
+		let white = Color::new(1.0, 1.0, 1.0);
+		let mut sampler = Sampler::new(5);
+		for (metallic, roughness) in [(0.0, 0.1), (0.0, 0.9), (1.0, 0.2), (1.0, 1.0), (0.5, 0.5)] {
+			let m = Microfacet::new(metallic, roughness);
+			for eye in [Vector::new(0.0, 1.0, 0.0), Vector::new(1.0, 0.3, 0.0).normalise()] {
+				let n_samples = 20000;
+				let mut albedo = Color::new(0.0, 0.0, 0.0);
+				for _ in 0..n_samples {
+					let u = (sampler.next_f64(), sampler.next_f64(), sampler.next_f64());
+					if let Some((_, weight)) = m.sample(white, 1.5, &n, &eye, u) {
+						albedo = albedo + weight;
+					}
+				}
+				let albedo = albedo.red / n_samples as f64;
+				assert!(albedo <= 1.01, "albedo {} for {:?}", albedo, m);
+				assert!(albedo > 0.2, "albedo {} for {:?}", albedo, m);
+			}
+		}
+	}
+}
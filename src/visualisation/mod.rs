@@ -1,6 +1,19 @@
+pub mod aov;
+pub mod bump;
 pub mod camera;
 pub mod canvas;
+pub mod denoise;
+pub mod dispersion;
+pub mod environment;
+pub mod integrator;
 pub mod intersection;
 pub mod lights;
 pub mod materials;
+pub mod medium;
+pub mod microfacet;
+pub mod photon_map;
+pub mod progress;
+pub mod progressive;
+pub mod spectrum;
+pub mod voxel_grid;
 pub mod world;
@@ -0,0 +1,265 @@
+use crate::{
+	intersection::hit,
+	primitives::{
+		color::Color,
+		point::Point,
+		ray::Ray,
+		sampler::{uniform_sample_sphere, Sampler},
+		vector::Vector,
+	},
+	visualisation::{intersection::Lobe, world::World},
+};
+use std::f64::consts::PI;
+
+// bounces a photon may take before it is dropped
+const PHOTON_DEPTH: usize = 8;
+
+// Light carried by a single photon, `direction` is the way it was travelling when it landed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Photon {
+	pub position: Point,
+	pub direction: Vector,
+	pub power: Color,
+}
+
+// Photons stored in a balanced kd-tree. The tree is implicit: every slice of `photons` has its
+// splitting photon in the middle, and `axes` holds the axis it splits along.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotonMap {
+	photons: Vec<Photon>,
+	axes: Vec<usize>,
+	// radius of the density estimation lookups
+	pub radius: f64,
+}
+
+impl PhotonMap {
+	pub fn new(mut photons: Vec<Photon>, radius: f64) -> Self {
+		let mut axes = vec![0; photons.len()];
+		build(&mut photons, &mut axes);
+		Self { photons, axes, radius }
+	}
+
+	// Traces `count` photons from the world's light and keeps the ones which reach a diffuse
+	// surface through at least one reflection or refraction, i.e. the caustics
+	pub fn caustics(world: &World, count: usize, radius: f64, seed: u64) -> Result<Self, String> {
+		let mut sampler = Sampler::new(seed);
+		let mut stored = vec![];
+		let light = &world.light;
+
+		for _ in 0..count {
+			let (u1, u2) = sampler.next_2d();
+			let direction = uniform_sample_sphere(u1, u2);
+			let mut power =
+				light.intensity_at(&(light.position + direction)) * (4.0 * PI / count as f64);
+			let mut ray = Ray::new(light.position, direction);
+			let mut travelled = 0.0;
+			let mut specular = false;
+
+			for _ in 0..PHOTON_DEPTH {
+				let xs = ray.intersect_world(world)?;
+				let intersection = match hit(&xs) {
+					Some(intersection) => intersection,
+					None => break,
+				};
+				let comps = ray.prepare_computations(intersection, Some(&xs));
+				travelled += (comps.point - ray.origin).magnitude();
+				power = power * comps.transmittance();
+
+				let material = comps.object.material();
+				if material.volume.is_some() {
+					ray = Ray::new(comps.under_point, ray.direction);
+					continue
+				}
+				match comps.sample_lobe(material, &mut sampler) {
+					// point lights do not fall off with distance, so the photon gets back what
+					// spreading over its path took away
+					Some(Lobe::Surface { .. }) => {
+						if specular {
+							stored.push(Photon {
+								position: comps.point,
+								direction: ray.direction,
+								power: power * (travelled * travelled),
+							});
+						}
+						break
+					},
+					Some(Lobe::Specular { ray: next, weight }) => {
+						specular = true;
+						power = power * weight;
+						ray = next;
+					},
+					None => break,
+				}
+			}
+		}
+		Ok(Self::new(stored, radius))
+	}
+
+	pub fn len(&self) -> usize {
+		self.photons.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.photons.is_empty()
+	}
+
+	// All photons closer than `radius` to `point`
+	pub fn within(&self, point: &Point, radius: f64) -> Vec<&Photon> {
+		let mut found = vec![];
+		search(&self.photons, &self.axes, point, radius * radius, &mut found);
+		found
+	}
+
+	// Light arriving on the side of `normal` per unit area around `point`
+	pub fn irradiance(&self, point: &Point, normal: &Vector) -> Color {
+		self.within(point, self.radius)
+			.into_iter()
+			.filter(|photon| photon.direction.dot(normal) < 0.0)
+			.fold(Color::new(0.0, 0.0, 0.0), |sum, photon| sum + photon.power) *
+			(1.0 / (PI * self.radius * self.radius))
+	}
+}
+
+fn coordinate(point: &Point, axis: usize) -> f64 {
+	match axis {
+		0 => point.tuple.x,
+		1 => point.tuple.y,
+		_ => point.tuple.z,
+	}
+}
+
+// splits along the axis of largest extent at the median
+fn build(photons: &mut [Photon], axes: &mut [usize]) {
+	if photons.is_empty() {
+		return
+	}
+	let extent = |axis| {
+		let values = photons.iter().map(|p| coordinate(&p.position, axis));
+		values.clone().fold(f64::NEG_INFINITY, f64::max) - values.fold(f64::INFINITY, f64::min)
+	};
+	let axis = (0..3).max_by(|&a, &b| extent(a).total_cmp(&extent(b))).unwrap_or(0);
+	photons.sort_by(|p1, p2| {
+		coordinate(&p1.position, axis).total_cmp(&coordinate(&p2.position, axis))
+	});
+
+	let mid = photons.len() / 2;
+	axes[mid] = axis;
+	let (left, right) = photons.split_at_mut(mid);
+	let (left_axes, right_axes) = axes.split_at_mut(mid);
+	build(left, left_axes);
+	build(&mut right[1..], &mut right_axes[1..]);
+}
+
+fn search<'a>(
+	photons: &'a [Photon],
+	axes: &[usize],
+	point: &Point,
+	radius_squared: f64,
+	found: &mut Vec<&'a Photon>,
+) {
+	if photons.is_empty() {
+		return
+	}
+	let mid = photons.len() / 2;
+	let photon = &photons[mid];
+	let offset = *point - photon.position;
+	if offset.dot(&offset) <= radius_squared {
+		found.push(photon);
+	}
+
+	let axis = axes[mid];
+	let delta = coordinate(point, axis) - coordinate(&photon.position, axis);
+	let (near, far) = if delta < 0.0 {
+		((&photons[..mid], &axes[..mid]), (&photons[mid + 1..], &axes[mid + 1..]))
+	} else {
+		((&photons[mid + 1..], &axes[mid + 1..]), (&photons[..mid], &axes[..mid]))
+	};
+	search(near.0, near.1, point, radius_squared, found);
+	if delta * delta <= radius_squared {
+		search(far.0, far.1, point, radius_squared, found);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		primitives::transformations::*,
+		shapes::{plane::Plane, shape::ConcreteShape, spheres::Sphere},
+		visualisation::lights::PointLight,
+	};
+
+	#[test]
+	fn kd_tree_test() {
+		let mut sampler = Sampler::new(3);
+		let photons: Vec<_> = (0..500)
+			.map(|_| Photon {
+				position: Point::new(sampler.next_f64(), sampler.next_f64(), sampler.next_f64()),
+				direction: Vector::new(0.0, -1.0, 0.0),
+				power: Color::new(1.0, 1.0, 1.0),
+			})
+			.collect();
+		let map = PhotonMap::new(photons.clone(), 0.1);
+		assert_eq!(map.len(), 500);
+
+		// the tree finds exactly the photons a linear scan does
+		for _ in 0..20 {
+			let point = Point::new(sampler.next_f64(), sampler.next_f64(), sampler.next_f64());
+			let mut expected: Vec<_> = photons
+				.iter()
+				.filter(|p| (p.position - point).magnitude() <= 0.2)
+				.map(|p| p.position.tuple.x)
+				.collect();
+			let mut found: Vec<_> =
+				map.within(&point, 0.2).into_iter().map(|p| p.position.tuple.x).collect();
+			expected.sort_by(f64::total_cmp);
+			found.sort_by(f64::total_cmp);
+			assert_eq!(found, expected);
+		}
+
+		// photons landing on the back of the surface are not counted
+		let point = Point::new(0.5, 0.5, 0.5);
+		let up = Vector::new(0.0, 1.0, 0.0);
+		assert!(map.irradiance(&point, &up).red > 0.0);
+		assert_eq!(map.irradiance(&point, &-up), Color::new(0.0, 0.0, 0.0));
+		assert!(PhotonMap::new(vec![], 0.1).within(&point, 1.0).is_empty());
+	}
+
+	#[test]
+	fn caustics_test() {
+		let floor = Plane::default();
+		let mut glass = Sphere::new_glass_sphere();
+		glass.set_transform(translation(0.0, 2.0, 0.0));
+		let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
+		let w = World::new(vec![Box::new(floor)], light.clone());
+
+		// without anything specular there are no caustics
+		assert!(PhotonMap::caustics(&w, 1000, 0.2, 0).unwrap().is_empty());
+
+		let w = World::new(vec![Box::new(Plane::default()), Box::new(glass)], light);
+		let map = PhotonMap::caustics(&w, 20000, 0.2, 0).unwrap();
+		assert!(!map.is_empty());
+		assert!(map.photons.iter().all(|p| p.direction.tuple.y < 0.0));
+
+		// the sphere focuses light below its centre and nothing lands far away from it
+		let up = Vector::new(0.0, 1.0, 0.0);
+		let focus = map.irradiance(&Point::new(0.0, 0.0, 0.0), &up);
+		assert!(focus.red > 1.0, "{:?}", focus);
+		assert_eq!(map.irradiance(&Point::new(5.0, 0.0, 0.0), &up), Color::new(0.0, 0.0, 0.0));
+
+		// the same seed gives the same map
+		assert_eq!(map, PhotonMap::caustics(&w, 20000, 0.2, 0).unwrap());
+
+		// without the map whitted shading lets the light straight through the clear glass, with it
+		// the glass blocks the direct light and the focused photons take its place
+		let mut w = w;
+		let r = Ray::new(Point::new(0.0, 0.5, -0.5), Vector::new(0.0, -1.0, 1.0).normalise());
+		let straight = w.color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(straight, Color::new(1.0, 1.0, 1.0), epsilon = 1e-9);
+		w.caustics = Some(map);
+		let focused = w.color_at(&r, None).unwrap();
+		assert!(focused.red > 1.0, "{:?}", focused);
+		let beside = Ray::new(Point::new(0.8, 0.5, -0.5), Vector::new(0.0, -1.0, 1.0).normalise());
+		assert!(w.color_at(&beside, None).unwrap().red < focused.red);
+	}
+}
@@ -0,0 +1,89 @@
+use crate::visualisation::canvas::Canvas;
+use std::{
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+// Shared flag asking a running render to stop, clones refer to the same flag so one can be handed
+// to another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+	cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+}
+
+// How far a render has got, reported after every row
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+	pub rows_done: usize,
+	pub rows: usize,
+	pub elapsed: Duration,
+	// time left if the remaining rows take as long as the finished ones
+	pub eta: Option<Duration>,
+	// camera rays traced per second, bounces and shadow rays are not counted
+	pub rays_per_second: f64,
+}
+
+impl Progress {
+	pub fn new(rows_done: usize, rows: usize, rays: usize, elapsed: Duration) -> Self {
+		let seconds = elapsed.as_secs_f64();
+		let eta =
+			(rows_done > 0).then(|| elapsed.mul_f64((rows - rows_done) as f64 / rows_done as f64));
+		let rays_per_second = if seconds > 0.0 { rays as f64 / seconds } else { 0.0 };
+		Self { rows_done, rows, elapsed, eta, rays_per_second }
+	}
+
+	pub fn fraction(&self) -> f64 {
+		if self.rows == 0 {
+			1.0
+		} else {
+			self.rows_done as f64 / self.rows as f64
+		}
+	}
+}
+
+// Outcome of a render which may have been cancelled, rows which were not reached stay black
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderReport {
+	pub image: Canvas,
+	pub cancelled: bool,
+	pub progress: Progress,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn progress_test() {
+		let token = CancellationToken::new();
+		let shared = token.clone();
+		assert!(!token.is_cancelled());
+		shared.cancel();
+		assert!(token.is_cancelled());
+
+		let p = Progress::new(1, 4, 100, Duration::from_secs(2));
+		assert_eq!(p.eta, Some(Duration::from_secs(6)));
+		assert_eq!(p.rays_per_second, 50.0);
+		assert_eq!(p.fraction(), 0.25);
+
+		let p = Progress::new(0, 4, 0, Duration::ZERO);
+		assert_eq!((p.eta, p.rays_per_second, p.fraction()), (None, 0.0, 0.0));
+	}
+}
@@ -0,0 +1,150 @@
+use crate::{primitives::color::Color, visualisation::canvas::Canvas};
+use std::time::Duration;
+
+// When a progressive render stops. At least one pass is always rendered, and at least two are
+// needed before the noise can be estimated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progressive {
+	pub max_passes: usize,
+	// root mean square standard error of the pixel luminances to reach
+	pub target_noise: Option<f64>,
+	pub time_budget: Option<Duration>,
+}
+
+impl Progressive {
+	pub fn new(max_passes: usize) -> Self {
+		Self { max_passes, target_noise: None, time_budget: None }
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+	Converged,
+	TimeBudget,
+	MaxPasses,
+}
+
+// State of a progressive render after a pass, handed to the pass callback
+#[derive(Debug, Clone, Copy)]
+pub struct PassSnapshot<'a> {
+	// number of passes accumulated so far, starting at 1
+	pub pass: usize,
+	pub image: &'a Canvas,
+	pub noise: f64,
+	pub elapsed: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressiveRender {
+	pub image: Canvas,
+	// variance of every pixel's mean, per channel
+	pub variance: Canvas,
+	pub passes: usize,
+	pub noise: f64,
+	pub stop: StopReason,
+}
+
+// Running mean and variance of every pixel over the passes, using Welford's algorithm
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+	mean: Canvas,
+	squares: Vec<Color>,
+	luminance_squares: Vec<f64>,
+	luminance_mean: Vec<f64>,
+	passes: usize,
+}
+
+impl Accumulator {
+	pub fn new(width: usize, height: usize) -> Self {
+		Self {
+			mean: Canvas::new(width, height),
+			squares: vec![Color::new(0.0, 0.0, 0.0); width * height],
+			luminance_squares: vec![0.0; width * height],
+			luminance_mean: vec![0.0; width * height],
+			passes: 0,
+		}
+	}
+
+	pub fn add(&mut self, pass: &Canvas) -> Result<(), String> {
+		if (pass.width, pass.height) != (self.mean.width, self.mean.height) {
+			return Err(format!(
+				"Pass of size {}x{} does not match accumulator of size {}x{}",
+				pass.width, pass.height, self.mean.width, self.mean.height
+			))
+		}
+		self.passes += 1;
+		let n = self.passes as f64;
+		for (i, value) in pass.pixels.iter().enumerate() {
+			let mean = self.mean.pixels[i];
+			let delta = *value - mean;
+			let mean = mean + delta * (1.0 / n);
+			self.squares[i] = self.squares[i] + delta * (*value - mean);
+			self.mean.pixels[i] = mean;
+
+			let luminance = value.luminance();
+			let delta = luminance - self.luminance_mean[i];
+			self.luminance_mean[i] += delta / n;
+			self.luminance_squares[i] += delta * (luminance - self.luminance_mean[i]);
+		}
+		Ok(())
+	}
+
+	pub fn passes(&self) -> usize {
+		self.passes
+	}
+
+	pub fn image(&self) -> &Canvas {
+		&self.mean
+	}
+
+	// Sample variance of the mean of every pixel, zero until there are two passes
+	pub fn variance(&self) -> Canvas {
+		let mut variance = Canvas::new(self.mean.width, self.mean.height);
+		if self.passes > 1 {
+			let n = self.passes as f64;
+			for (i, squares) in self.squares.iter().enumerate() {
+				variance.pixels[i] = *squares * (1.0 / ((n - 1.0) * n));
+			}
+		}
+		variance
+	}
+
+	// Root mean square standard error of the pixel luminances, infinite until there are two
+	// passes
+	pub fn noise(&self) -> f64 {
+		if self.passes < 2 || self.luminance_squares.is_empty() {
+			return f64::INFINITY
+		}
+		let n = self.passes as f64;
+		let total: f64 = self.luminance_squares.iter().map(|s| s / ((n - 1.0) * n)).sum();
+		(total / self.luminance_squares.len() as f64).sqrt()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accumulator_test() {
+		let mut acc = Accumulator::new(2, 1);
+		let mut pass = Canvas::new(2, 1);
+		pass.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+		acc.add(&pass).unwrap();
+		assert_eq!(acc.noise(), f64::INFINITY);
+		assert_eq!(acc.variance().pixel_at(0, 0), Color::new(0.0, 0.0, 0.0));
+
+		pass.write_pixel(0, 0, Color::new(3.0, 1.0, 1.0));
+		acc.add(&pass).unwrap();
+		pass.write_pixel(0, 0, Color::new(5.0, 1.0, 1.0));
+		acc.add(&pass).unwrap();
+		assert_eq!(acc.passes(), 3);
+		assert_eq!(acc.image().pixel_at(0, 0), Color::new(3.0, 1.0, 1.0));
+		// values 1, 3, 5 have a sample variance of 4, so their mean one of 4 / 3
+		approx::assert_relative_eq!(acc.variance().pixel_at(0, 0).red, 4.0 / 3.0);
+		assert_eq!(acc.variance().pixel_at(1, 0), Color::new(0.0, 0.0, 0.0));
+		approx::assert_relative_eq!(acc.noise(), (0.2126 * 0.2126 * 4.0 / 3.0 / 2.0_f64).sqrt());
+
+		assert!(acc.add(&Canvas::new(1, 1)).is_err());
+	}
+}
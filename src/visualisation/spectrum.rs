@@ -0,0 +1,224 @@
+use crate::primitives::color::Color;
+use std::sync::OnceLock;
+
+// Visible range sampled by spectral renders, in nanometres
+pub const WAVELENGTH_MIN: f64 = 380.0;
+pub const WAVELENGTH_MAX: f64 = 730.0;
+
+// RGB colours are upsampled into three bands of constant value, blue below the first edge, red
+// above the second. Primaries come back out of `Spectrum::to_rgb` within a few percent.
+const BLUE_EDGE: f64 = 490.0;
+const RED_EDGE: f64 = 590.0;
+
+// wavelengths integrated when converting a spectrum to RGB
+const INTEGRATION_STEPS: usize = 350;
+
+// Spectral distribution of a reflectance or an emitter
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Spectrum {
+	Constant(f64),
+	// evenly spaced samples from `start` to `end`, linearly interpolated and held outside
+	Sampled { start: f64, end: f64, values: Vec<f64> },
+	// Planck's law at `temperature` kelvin, normalised to 1 at the peak
+	Blackbody { temperature: f64 },
+	// an RGB colour upsampled into three bands
+	Rgb(Color),
+}
+
+impl Spectrum {
+	pub fn new_sampled(start: f64, end: f64, values: Vec<f64>) -> Self {
+		Self::Sampled { start, end, values }
+	}
+
+	pub fn new_blackbody(temperature: f64) -> Self {
+		Self::Blackbody { temperature }
+	}
+
+	pub fn value(&self, wavelength: f64) -> f64 {
+		match self {
+			Self::Constant(value) => *value,
+			Self::Sampled { start, end, values } => match values.len() {
+				0 => 0.0,
+				1 => values[0],
+				n => {
+					let x = ((wavelength - start) / (end - start) * (n - 1) as f64)
+						.clamp(0.0, (n - 1) as f64);
+					let i = (x.floor() as usize).min(n - 2);
+					values[i] + (values[i + 1] - values[i]) * (x - i as f64)
+				},
+			},
+			Self::Blackbody { temperature } => {
+				// Wien's displacement law gives the peak
+				let peak = 2.897_771_955e6 / temperature;
+				planck(wavelength, *temperature) / planck(peak, *temperature)
+			},
+			Self::Rgb(color) => project(*color, wavelength),
+		}
+	}
+
+	// Linear sRGB colour of the spectrum, a constant spectrum of 1 is white
+	pub fn to_rgb(&self) -> Color {
+		if let Self::Rgb(color) = self {
+			return *color
+		}
+		let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / INTEGRATION_STEPS as f64;
+		let mut sum = Color::new(0.0, 0.0, 0.0);
+		for i in 0..INTEGRATION_STEPS {
+			let wavelength = WAVELENGTH_MIN + (i as f64 + 0.5) * step;
+			sum = sum + wavelength_to_rgb(wavelength) * self.value(wavelength);
+		}
+		sum * (1.0 / INTEGRATION_STEPS as f64)
+	}
+}
+
+// Value at `wavelength` of the spectrum upsampled from an RGB colour
+pub fn project(color: Color, wavelength: f64) -> f64 {
+	if wavelength < BLUE_EDGE {
+		color.blue
+	} else if wavelength < RED_EDGE {
+		color.green
+	} else {
+		color.red
+	}
+}
+
+// Linear sRGB estimate from the radiance carried by a ray of a single wavelength. Inputs given
+// as RGB colours were carried along channel by channel, so the channel upsampled at the
+// wavelength is the radiance there.
+pub fn film_response(radiance: Color, wavelength: f64) -> Color {
+	wavelength_to_rgb(wavelength) * project(radiance, wavelength)
+}
+
+// Spectral radiance of a black body, up to a constant factor
+fn planck(wavelength: f64, temperature: f64) -> f64 {
+	let l = wavelength * 1e-9;
+	1.0 / (l.powi(5) * ((1.438_776_877e-2 / (l * temperature)).exp() - 1.0))
+}
+
+fn lobe(wavelength: f64, mean: f64, below: f64, above: f64) -> f64 {
+	let sigma = if wavelength < mean { below } else { above };
+	(-0.5 * ((wavelength - mean) / sigma).powi(2)).exp()
+}
+
+// CIE 1931 2° colour matching functions, using the multi-lobe fit from Wyman, Sloan and Shirley,
+// "Simple Analytic Approximations to the CIE XYZ Color Matching Functions"
+pub fn cie_xyz(wavelength: f64) -> (f64, f64, f64) {
+	let l = wavelength;
+	let x = 1.056 * lobe(l, 599.8, 37.9, 31.0) + 0.362 * lobe(l, 442.0, 16.0, 26.7) -
+		0.065 * lobe(l, 501.1, 20.4, 26.2);
+	let y = 0.821 * lobe(l, 568.8, 46.9, 40.5) + 0.286 * lobe(l, 530.9, 16.3, 31.1);
+	let z = 1.217 * lobe(l, 437.0, 11.8, 36.0) + 0.681 * lobe(l, 459.0, 26.0, 13.8);
+	(x, y, z)
+}
+
+// CIE XYZ to linear sRGB with the D65 white point
+pub fn xyz_to_rgb(x: f64, y: f64, z: f64) -> Color {
+	Color::new(
+		3.2406 * x - 1.5372 * y - 0.4986 * z,
+		-0.9689 * x + 1.8758 * y + 0.0415 * z,
+		0.0557 * x - 0.2040 * y + 1.0570 * z,
+	)
+}
+
+// Linear sRGB contribution of a single wavelength, scaled so that averaging it over wavelengths
+// drawn uniformly from the visible range turns a constant spectrum of 1 into white
+pub fn wavelength_to_rgb(wavelength: f64) -> Color {
+	let (x, y, z) = cie_xyz(wavelength);
+	xyz_to_rgb(x, y, z) * white_balance()
+}
+
+fn white_balance() -> Color {
+	static WHITE_BALANCE: OnceLock<Color> = OnceLock::new();
+	*WHITE_BALANCE.get_or_init(|| {
+		let step = (WAVELENGTH_MAX - WAVELENGTH_MIN) / INTEGRATION_STEPS as f64;
+		let mut white = Color::new(0.0, 0.0, 0.0);
+		for i in 0..INTEGRATION_STEPS {
+			let (x, y, z) = cie_xyz(WAVELENGTH_MIN + (i as f64 + 0.5) * step);
+			white = white + xyz_to_rgb(x, y, z);
+		}
+		let n = INTEGRATION_STEPS as f64;
+		Color::new(n / white.red, n / white.green, n / white.blue)
+	})
+}
+
+// Wavelength for the `i`th of `n` stratified samples, `u` in [0, 1) jitters it inside its stratum
+pub fn sample_wavelength(i: usize, n: usize, u: f64) -> f64 {
+	WAVELENGTH_MIN + (WAVELENGTH_MAX - WAVELENGTH_MIN) * (i as f64 + u) / n as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cie_test() {
+		// peaks of the matching functions
+		approx::assert_relative_eq!(
+			cie_xyz(568.8).1,
+			0.821 + 0.286 * lobe(568.8, 530.9, 16.3, 31.1)
+		);
+		assert!(cie_xyz(555.0).1 > 0.99);
+		assert!(cie_xyz(600.0).0 > 1.0);
+		assert!(cie_xyz(445.0).2 > 1.7);
+		// D65 white maps to sRGB white
+		approx::assert_relative_eq!(
+			xyz_to_rgb(0.95047, 1.0, 1.08883),
+			Color::new(1.0, 1.0, 1.0),
+			epsilon = 1e-3
+		);
+	}
+
+	#[test]
+	fn spectrum_test() {
+		approx::assert_relative_eq!(
+			Spectrum::Constant(1.0).to_rgb(),
+			Color::new(1.0, 1.0, 1.0),
+			epsilon = 1e-9
+		);
+		approx::assert_relative_eq!(
+			Spectrum::Constant(0.5).to_rgb(),
+			Color::new(0.5, 0.5, 0.5),
+			epsilon = 1e-9
+		);
+
+		// upsampled colours come back close to the original
+		for color in
+			[Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0), Color::new(0.2, 0.4, 0.8)]
+		{
+			let rgb = Color::new(color.red, color.green, color.blue);
+			let upsampled = Spectrum::new_sampled(
+				WAVELENGTH_MIN,
+				WAVELENGTH_MAX,
+				(0..=350).map(|i| project(rgb, WAVELENGTH_MIN + i as f64)).collect(),
+			);
+			approx::assert_relative_eq!(upsampled.to_rgb(), color, epsilon = 0.1);
+			assert_eq!(Spectrum::Rgb(color).to_rgb(), color);
+			assert_eq!(Spectrum::Rgb(color).value(450.0), color.blue);
+			assert_eq!(Spectrum::Rgb(color).value(650.0), color.red);
+		}
+
+		let sampled = Spectrum::new_sampled(400.0, 600.0, vec![0.0, 1.0, 0.5]);
+		assert_eq!(sampled.value(300.0), 0.0);
+		assert_eq!(sampled.value(450.0), 0.5);
+		assert_eq!(sampled.value(550.0), 0.75);
+		assert_eq!(sampled.value(700.0), 0.5);
+
+		// hot bodies are blue, cooler ones red
+		let hot = Spectrum::new_blackbody(10000.0).to_rgb();
+		let warm = Spectrum::new_blackbody(3000.0).to_rgb();
+		assert!(hot.blue > hot.red);
+		assert!(warm.red > warm.blue);
+		approx::assert_relative_eq!(
+			Spectrum::new_blackbody(5000.0).value(579.5),
+			1.0,
+			epsilon = 1e-6
+		);
+	}
+
+	#[test]
+	fn sample_wavelength_test() {
+		assert_eq!(sample_wavelength(0, 4, 0.0), WAVELENGTH_MIN);
+		approx::assert_relative_eq!(sample_wavelength(3, 4, 1.0), WAVELENGTH_MAX);
+		approx::assert_relative_eq!(sample_wavelength(1, 2, 0.0), 555.0);
+	}
+}
@@ -0,0 +1,185 @@
+use std::{fs, path::Path};
+
+// magic number starting binary grids, followed by the little endian u32 dimensions and f32 values
+const BINARY_MAGIC: &[u8] = b"VGRD";
+
+// Dense 3D grid of densities, stored x fastest then y then z. Values are sampled with trilinear
+// interpolation over the unit cube, each voxel centred in its cell.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct VoxelGrid {
+	pub width: usize,
+	pub height: usize,
+	pub depth: usize,
+	values: Vec<f64>,
+	max: f64,
+}
+
+impl VoxelGrid {
+	pub fn new(
+		width: usize,
+		height: usize,
+		depth: usize,
+		values: Vec<f64>,
+	) -> Result<Self, String> {
+		if width == 0 || height == 0 || depth == 0 {
+			return Err(format!(
+				"Voxel grid dimensions must be positive, got {width}x{height}x{depth}"
+			))
+		}
+		let count = width
+			.checked_mul(height)
+			.and_then(|n| n.checked_mul(depth))
+			.ok_or(format!("Voxel grid of {width}x{height}x{depth} is too large"))?;
+		if values.len() != count {
+			return Err(format!(
+				"Voxel grid of {}x{}x{} needs {} values, got {}",
+				width,
+				height,
+				depth,
+				count,
+				values.len()
+			))
+		}
+		if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+			return Err("Voxel densities must be finite and non negative".to_string())
+		}
+		let max = values.iter().cloned().fold(0.0, f64::max);
+		Ok(Self { width, height, depth, values, max })
+	}
+
+	pub fn load(path: &str) -> Result<Self, String> {
+		let bytes =
+			fs::read(Path::new(path)).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+		Self::from_bytes(&bytes)
+	}
+
+	// Binary grids start with `VGRD`, anything else is read as text
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.starts_with(BINARY_MAGIC) {
+			return Self::from_binary(&bytes[BINARY_MAGIC.len()..])
+		}
+		let text = std::str::from_utf8(bytes).map_err(|_| "Voxel grid is not valid text")?;
+		Self::from_text(text)
+	}
+
+	// Whitespace separated width, height and depth followed by the densities, `#` starts a
+	// comment running to the end of the line
+	pub fn from_text(text: &str) -> Result<Self, String> {
+		let mut tokens =
+			text.lines().flat_map(|l| l.split('#').next().unwrap_or("").split_whitespace());
+		let mut dimension = || -> Result<usize, String> {
+			let token = tokens.next().ok_or("Voxel grid is missing its dimensions")?;
+			token.parse().map_err(|_| format!("Invalid voxel grid dimension {}", token))
+		};
+		let (width, height, depth) = (dimension()?, dimension()?, dimension()?);
+		let values = tokens
+			.map(|t| t.parse::<f64>().map_err(|_| format!("Invalid voxel density {}", t)))
+			.collect::<Result<Vec<f64>, String>>()?;
+		Self::new(width, height, depth, values)
+	}
+
+	fn from_binary(bytes: &[u8]) -> Result<Self, String> {
+		if bytes.len() < 12 {
+			return Err("Voxel grid is missing its dimensions".to_string())
+		}
+		let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap()) as usize;
+		let (width, height, depth) = (u32_at(0), u32_at(4), u32_at(8));
+		let values = bytes[12..]
+			.chunks(4)
+			.map(|c| match c.try_into() {
+				Ok(c) => Ok(f32::from_le_bytes(c) as f64),
+				Err(_) => Err("Voxel grid ends inside a value".to_string()),
+			})
+			.collect::<Result<Vec<f64>, String>>()?;
+		Self::new(width, height, depth, values)
+	}
+
+	// Binary encoding read back by `from_bytes`
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = BINARY_MAGIC.to_vec();
+		for d in [self.width, self.height, self.depth] {
+			bytes.extend_from_slice(&(d as u32).to_le_bytes());
+		}
+		for v in &self.values {
+			bytes.extend_from_slice(&(*v as f32).to_le_bytes());
+		}
+		bytes
+	}
+
+	pub fn max(&self) -> f64 {
+		self.max
+	}
+
+	pub fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+		self.values[(z * self.height + y) * self.width + x]
+	}
+
+	// Density at (x, y, z) in the unit cube, zero outside of it
+	pub fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+		if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) || !(0.0..=1.0).contains(&z) {
+			return 0.0
+		}
+		// lower voxel and weight along one axis, held at the outer half voxels
+		let axis = |c: f64, size: usize| {
+			let p = (c * size as f64 - 0.5).clamp(0.0, (size - 1) as f64);
+			let i = (p.floor() as usize).min(size.saturating_sub(2));
+			(i, (i + 1).min(size - 1), p - i as f64)
+		};
+		let (x0, x1, fx) = axis(x, self.width);
+		let (y0, y1, fy) = axis(y, self.height);
+		let (z0, z1, fz) = axis(z, self.depth);
+		let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+		let plane = |z: usize| {
+			lerp(
+				lerp(self.voxel(x0, y0, z), self.voxel(x1, y0, z), fx),
+				lerp(self.voxel(x0, y1, z), self.voxel(x1, y1, z), fx),
+				fy,
+			)
+		};
+		lerp(plane(z0), plane(z1), fz)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn grid_test() {
+		assert!(VoxelGrid::new(2, 2, 2, vec![0.0; 7]).is_err());
+		assert!(VoxelGrid::new(0, 2, 2, vec![]).is_err());
+		assert!(VoxelGrid::new(1, 1, 1, vec![-1.0]).is_err());
+		assert!(VoxelGrid::new(usize::MAX, 2, 1, vec![0.0; 2]).is_err());
+
+		let grid = VoxelGrid::new(2, 2, 2, vec![0.0, 1.0, 0.0, 1.0, 2.0, 3.0, 2.0, 3.0]).unwrap();
+		assert_eq!(grid.max(), 3.0);
+		assert_eq!(grid.voxel(1, 0, 1), 3.0);
+
+		// voxel centres, interpolation between them and clamping in the outer half voxels
+		assert_eq!(grid.sample(0.25, 0.25, 0.25), 0.0);
+		assert_eq!(grid.sample(0.75, 0.75, 0.75), 3.0);
+		assert_eq!(grid.sample(0.5, 0.5, 0.5), 1.5);
+		assert_eq!(grid.sample(0.5, 0.1, 0.25), 0.5);
+		assert_eq!(grid.sample(0.0, 0.0, 1.0), 2.0);
+		assert_eq!(grid.sample(1.1, 0.5, 0.5), 0.0);
+
+		// a single voxel fills the whole cube
+		let single = VoxelGrid::new(1, 1, 1, vec![0.7]).unwrap();
+		assert_eq!(single.sample(0.1, 0.9, 0.5), 0.7);
+	}
+
+	#[test]
+	fn parse_test() {
+		let text = "# smoke\n2 1 1\n0.5 # left\n1.5\n";
+		let grid = VoxelGrid::from_bytes(text.as_bytes()).unwrap();
+		assert_eq!((grid.width, grid.height, grid.depth), (2, 1, 1));
+		assert_eq!(grid.voxel(1, 0, 0), 1.5);
+
+		assert_eq!(VoxelGrid::from_bytes(&grid.to_bytes()).unwrap(), grid);
+
+		assert!(VoxelGrid::from_text("2 1").is_err());
+		assert!(VoxelGrid::from_text("1 1 1 x").is_err());
+		assert!(VoxelGrid::from_bytes(&grid.to_bytes()[..13]).is_err());
+		assert!(VoxelGrid::load("does/not/exist.vgrd").is_err());
+	}
+}
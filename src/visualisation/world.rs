@@ -1,55 +1,198 @@
 use crate::{
-	intersection::{hit, IntersectionComputations},
-	primitives::{color::Color, point::Point, ray::Ray, transformations::*},
+	intersection::{hit, Intersection, IntersectionComputations},
+	primitives::{
+		color::Color, point::Point, ray::Ray, sampler::Sampler, transformations::*, vector::Vector,
+	},
 	shapes::{shape::ConcreteShape, spheres::Sphere},
-	visualisation::lights::PointLight,
+	visualisation::{
+		dispersion::CHANNEL_WAVELENGTHS,
+		environment::Environment,
+		integrator::AmbientOcclusion,
+		lights::PointLight,
+		medium::{distance_inside, Fog},
+		photon_map::PhotonMap,
+		spectrum::project,
+	},
 };
 
 pub struct World {
 	pub objects: Vec<Box<dyn ConcreteShape>>,
 	pub light: PointLight,
+	// seen by rays which miss every object, black by default
+	pub background: Environment,
+	// directions sampled on the background to light each hit in `shade_hit`, zero keeps the
+	// background out of Whitted lighting. The path tracer always samples it once per bounce.
+	pub environment_samples: usize,
+	// exponential fog between the camera and everything it sees, none by default
+	pub fog: Option<Fog>,
+	// darkens the ambient term of `shade_hit` in crevices, off by default
+	pub ambient_occlusion: Option<AmbientOcclusion>,
+	// caustic photons traced with `PhotonMap::caustics`, added on top of direct lighting
+	pub caustics: Option<PhotonMap>,
 }
 
 const REFLECTION_RECURSION_THRESHOLD: i32 = 3;
+// number of points sampled on every emissive object when shading a hit
+const AREA_LIGHT_SAMPLES: usize = 16;
+// points at which light scattered towards the eye is gathered when a ray crosses a volume
+const VOLUME_STEPS: usize = 32;
+// points at which the density is gathered between each of them and the light
+const LIGHT_STEPS: usize = 8;
 
 impl World {
 	pub fn new(objects: Vec<Box<dyn ConcreteShape>>, light: PointLight) -> Self {
-		Self { objects, light }
+		Self {
+			objects,
+			light,
+			background: Environment::default(),
+			environment_samples: 0,
+			fog: None,
+			ambient_occlusion: None,
+			caustics: None,
+		}
 	}
 
 	pub fn shade_hit(&self, comps: &IntersectionComputations, remaining: Option<i32>) -> Color {
-		let in_shadow = self.is_shadowed(comps.over_point).unwrap();
-		let surface = comps.object.material().lighting(
+		let shadow = if comps.object.flags().receive_shadows {
+			self.light_transmittance(comps.over_point, comps.wavelength).unwrap()
+		} else {
+			Color::new(1.0, 1.0, 1.0)
+		};
+		let material = comps.object.material().spectral(comps.wavelength);
+		let visibility = match &self.ambient_occlusion {
+			Some(ao) if material.ambient > 0.0 && comps.object.flags().receive_shadows => {
+				let mut sampler = Sampler::new(point_seed(&comps.point) ^ ao.seed);
+				ao.visibility(self, comps.over_point, &comps.normal, &mut sampler)
+					.expect("Could not compute ambient occlusion")
+			},
+			_ => 1.0,
+		};
+		let light = self.light.spectral(comps.wavelength);
+		let ambient = material.ambient_lighting(comps.object, &light, &comps.over_point);
+		let direct = material.direct_lighting(
 			comps.object,
-			&self.light,
+			&light,
 			&comps.over_point,
 			&comps.eye,
 			&comps.normal,
-			in_shadow,
+			shadow,
 		);
+		let surface = ambient * visibility + direct + self.caustic_lighting(comps);
+
+		let mut surface = surface + material.emissive;
+		let mut sampler = Sampler::new(point_seed(&comps.point));
+		surface = surface +
+			self.area_lighting(comps, &mut sampler, AREA_LIGHT_SAMPLES)
+				.expect("Could not compute area lighting");
+		if self.environment_samples > 0 {
+			let mut sampler = Sampler::new(point_seed(&comps.point));
+			surface = surface +
+				self.environment_lighting(comps, &mut sampler, self.environment_samples)
+					.expect("Could not compute environment lighting");
+		}
 		let reflected_color = self.reflected_color(comps, remaining);
 		let refracted_color = self.refracted_color(comps, remaining);
 
-		let material = comps.object.material();
 		if material.reflective > 0.0 && material.transparency > 0.0 {
-			let reflectance = comps.schlick();
-			surface + reflected_color * reflectance + refracted_color * (1.0 - reflectance)
+			let reflectance = comps.reflectance();
+			surface +
+				reflected_color * reflectance +
+				refracted_color * (Color::new(1.0, 1.0, 1.0) - reflectance)
 		} else {
 			surface + reflected_color + refracted_color
 		}
 	}
 
+	// Colour seen by a ray leaving the camera
 	pub fn color_at(&self, ray: &Ray, remaining: Option<i32>) -> Result<Color, String> {
-		let xs = ray.intersect_world(self)?;
+		self.trace(ray, remaining, true)
+	}
+
+	// Intersections with the objects a camera ray, or a bounced one if `camera` is false, can see
+	pub fn visible_intersections<'a>(
+		&'a self,
+		ray: &Ray,
+		camera: bool,
+	) -> Result<Vec<Intersection<'a>>, String> {
+		Ok(ray
+			.intersect_world(self)?
+			.into_iter()
+			.filter(|i| i.object.flags().visible(camera))
+			.collect())
+	}
+
+	fn trace(&self, ray: &Ray, remaining: Option<i32>, camera: bool) -> Result<Color, String> {
+		let xs = self.visible_intersections(ray, camera)?;
 		let hits = hit(&xs);
-		let color = match hits {
+		let (color, distance) = match hits {
 			Some(intersection) => {
 				let comps = ray.prepare_computations(&intersection, Some(&xs));
-				self.shade_hit(&comps, remaining)
+				let surface = if comps.object.material().volume.is_some() {
+					// volume boundaries are invisible, the ray carries on through them
+					let through =
+						Ray::new(comps.under_point, ray.direction).with_wavelength(ray.wavelength);
+					self.trace(&through, remaining, camera)?
+				} else {
+					self.shade_hit(&comps, remaining)
+				};
+				let mut color = surface * comps.transmittance();
+				if let Some(medium) = comps.medium.filter(|m| m.material().volume.is_some()) {
+					let (scattered, transmittance) = self.march_volume(ray, comps.t, medium)?;
+					color = color * transmittance + scattered;
+				}
+				(color, comps.t * ray.direction.magnitude())
 			},
-			_ => Color::new(0.0, 0.0, 0.0),
+			_ => (self.background.color_at(&ray.direction), f64::INFINITY),
 		};
-		Ok(color)
+		Ok(match &self.fog {
+			Some(fog) => fog.apply(color, distance),
+			None => color,
+		})
+	}
+
+	// Ray marches the volume of `medium` between the origin of `ray` and `ray.position(t)`.
+	// Returns the light from the point light scattered once towards the origin, dimmed on its way
+	// in and out of the medium, together with the transmittance of the whole segment.
+	pub fn march_volume(
+		&self,
+		ray: &Ray,
+		t: f64,
+		medium: &dyn ConcreteShape,
+	) -> Result<(Color, f64), String> {
+		let volume = medium.material().volume.as_ref().ok_or("Medium has no volume")?;
+		let light = self.light.spectral(ray.wavelength);
+		let speed = ray.direction.magnitude();
+		let towards = ray.direction / speed;
+		let step = t * speed / VOLUME_STEPS as f64;
+		let mut color = Color::new(0.0, 0.0, 0.0);
+		let mut depth = 0.0;
+		for i in 0..VOLUME_STEPS {
+			let start = ray.origin + towards * (i as f64 * step);
+			let point = start + towards * (step / 2.0);
+			let before = volume.optical_depth(medium, &start, &towards, step / 2.0, 1);
+			let after = volume.optical_depth(medium, &point, &towards, step / 2.0, 1);
+			let density = volume.density_at(medium, &point);
+			let transmittance = if density > 0.0 {
+				self.shadow_transmittance(point, light.position, ray.wavelength)?
+			} else {
+				Color::new(0.0, 0.0, 0.0)
+			};
+			if transmittance != Color::new(0.0, 0.0, 0.0) {
+				let to_light = light.position - point;
+				let reach = distance_inside(medium, &point, &to_light.normalise())?
+					.min(to_light.magnitude());
+				let shadow =
+					volume.optical_depth(medium, &point, &to_light.normalise(), reach, LIGHT_STEPS);
+				let phase = volume.phase(&-to_light.normalise(), &-towards);
+				color = color +
+					light.intensity_at(&point) *
+						transmittance *
+						volume.albedo *
+						(density * step * phase * (-(depth + before + shadow)).exp());
+			}
+			depth += before + after;
+		}
+		Ok((color, (-depth).exp()))
 	}
 
 	pub fn reflected_color(
@@ -65,9 +208,14 @@ impl World {
 			return Color::new(0.0, 0.0, 0.0)
 		}
 
-		let reflect_ray = Ray::new(comps.over_point, comps.reflection_vector);
+		let reflect_ray =
+			Ray::new(comps.over_point, comps.reflection_vector).with_wavelength(comps.wavelength);
 		let color = self
-			.color_at(&reflect_ray, Some(remaining.unwrap_or(REFLECTION_RECURSION_THRESHOLD) - 1))
+			.trace(
+				&reflect_ray,
+				Some(remaining.unwrap_or(REFLECTION_RECURSION_THRESHOLD) - 1),
+				false,
+			)
 			.expect("Could not compute reflected color");
 		color * comps.object.material().reflective
 	}
@@ -84,43 +232,235 @@ impl World {
 			return Color::new(0.0, 0.0, 0.0)
 		}
 
-		// Snell's law
-		let ratio = comps.n1 / comps.n2;
-		let cos_i = comps.eye.dot(&comps.normal);
-		let sin2_t = ratio.powi(2) * (1.0 - cos_i.powi(2));
-		if sin2_t > 1.0 {
-			return Color::new(0.0, 0.0, 0.0)
+		// every channel bends by its own angle and is traced separately
+		if comps.is_dispersive() {
+			let [red, green, blue] = CHANNEL_WAVELENGTHS
+				.map(|w| self.refracted_color(&comps.at_wavelength(w), remaining));
+			return Color::new(red.red, green.green, blue.blue)
 		}
 
-		let cos_t = (1.0 - sin2_t).sqrt();
-		let direction = comps.normal * (ratio * cos_i - cos_t) - comps.eye * ratio;
-		let refract_ray = Ray::new(comps.under_point, direction);
+		let direction = match comps.refraction_direction() {
+			Some(direction) => direction,
+			None => return Color::new(0.0, 0.0, 0.0),
+		};
+		let refract_ray = Ray::new(comps.under_point, direction).with_wavelength(comps.wavelength);
 		let color = self
-			.color_at(&refract_ray, Some(remaining.unwrap_or(REFLECTION_RECURSION_THRESHOLD) - 1))
+			.trace(
+				&refract_ray,
+				Some(remaining.unwrap_or(REFLECTION_RECURSION_THRESHOLD) - 1),
+				false,
+			)
 			.expect("Could not compute refracted color");
 		color * comps.object.material().transparency
 	}
 
-	pub fn is_shadowed(&self, point: Point) -> Result<bool, String> {
-		let v = self.light.position - point;
-		let distance = v.magnitude();
-		let direction = v.normalise();
+	// Light reflected at the hit point from objects with an emissive material. Every emitter whose
+	// surface can be sampled is treated as an area light and sampled `samples` times.
+	pub fn area_lighting(
+		&self,
+		comps: &IntersectionComputations,
+		sampler: &mut Sampler,
+		samples: usize,
+	) -> Result<Color, String> {
+		let material = comps.object.material().spectral(comps.wavelength);
+		let mut color = Color::new(0.0, 0.0, 0.0);
+		let mut emitters = self
+			.objects
+			.iter()
+			.filter(|o| o.material().is_emissive() && o.can_sample_surface())
+			.peekable();
+		if material.surface_weight() == 0.0 || samples == 0 || emitters.peek().is_none() {
+			return Ok(color)
+		}
+		let albedo = material.color_at(comps.object, &comps.over_point);
+		let receive_shadows = comps.object.flags().receive_shadows;
+
+		for emitter in emitters {
+			let mut emitted = Color::new(0.0, 0.0, 0.0);
+			for _ in 0..samples {
+				let (u1, u2) = sampler.next_2d();
+				let sample = match emitter.sample_surface(u1, u2) {
+					Some(sample) => sample,
+					None => break,
+				};
+				let to_light = sample.point - comps.over_point;
+				let distance = to_light.magnitude();
+				let direction = to_light / distance;
+				let cos_surface = direction.dot(&comps.normal);
+				// emitters shine from both sides, back faces of closed shapes are occluded anyway
+				let cos_light = direction.dot(&sample.normal).abs();
+				if cos_surface <= 0.0 {
+					continue
+				}
+				let transmittance = if receive_shadows {
+					self.shadow_transmittance(
+						comps.over_point,
+						sample.point - direction * 1e-6,
+						comps.wavelength,
+					)?
+				} else {
+					Color::new(1.0, 1.0, 1.0)
+				};
+				if transmittance == Color::new(0.0, 0.0, 0.0) {
+					continue
+				}
+				let geometry = cos_surface * cos_light / (distance * distance * sample.pdf);
+				let brdf = material.brdf(albedo, &comps.normal, &comps.eye, &direction);
+				emitted = emitted + emitter.material().emissive * brdf * transmittance * geometry;
+			}
+			color = color + emitted * (1.0 / samples as f64);
+		}
+		Ok(color)
+	}
+
+	// Light focused onto the hit point by reflective and refractive objects, estimated from the
+	// density of the caustic photons around it
+	pub fn caustic_lighting(&self, comps: &IntersectionComputations) -> Color {
+		let caustics = match &self.caustics {
+			Some(caustics) => caustics,
+			None => return Color::new(0.0, 0.0, 0.0),
+		};
+		let material = comps.object.material().spectral(comps.wavelength);
+		let albedo = material.color_at(comps.object, &comps.over_point);
+		let mut color = Color::new(0.0, 0.0, 0.0);
+		for photon in caustics.within(&comps.over_point, caustics.radius) {
+			let light_dir = -photon.direction;
+			if light_dir.dot(&comps.normal) <= 0.0 {
+				continue
+			}
+			let power = match comps.wavelength {
+				Some(wavelength) => {
+					let p = project(photon.power, wavelength);
+					Color::new(p, p, p)
+				},
+				None => photon.power,
+			};
+			color = color + material.brdf(albedo, &comps.normal, &comps.eye, &light_dir) * power;
+		}
+		// the brdf divides by PI, which point light shading does not, and so cancels the PI of the
+		// lookup disc
+		color * (1.0 / (caustics.radius * caustics.radius))
+	}
+
+	// Image based lighting: light arriving from the background, which is sampled `samples` times
+	// with shadow rays cast against the objects
+	pub fn environment_lighting(
+		&self,
+		comps: &IntersectionComputations,
+		sampler: &mut Sampler,
+		samples: usize,
+	) -> Result<Color, String> {
+		let material = comps.object.material().spectral(comps.wavelength);
+		let albedo = material.color_at(comps.object, &comps.over_point);
+		let mut color = Color::new(0.0, 0.0, 0.0);
+		if material.surface_weight() == 0.0 || samples == 0 {
+			return Ok(color)
+		}
+		let receive_shadows = comps.object.flags().receive_shadows;
+
+		for _ in 0..samples {
+			let (u1, u2) = sampler.next_2d();
+			let sample = match self.background.sample(u1, u2) {
+				Some(sample) => sample,
+				None => return Ok(color),
+			};
+			let cos_surface = sample.direction.dot(&comps.normal);
+			if cos_surface <= 0.0 {
+				continue
+			}
+			let transmittance = if receive_shadows {
+				self.transmittance_along(
+					comps.over_point,
+					sample.direction,
+					f64::INFINITY,
+					comps.wavelength,
+				)?
+			} else {
+				Color::new(1.0, 1.0, 1.0)
+			};
+			let brdf = material.brdf(albedo, &comps.normal, &comps.eye, &sample.direction);
+			color = color + sample.radiance * brdf * transmittance * (cos_surface / sample.pdf);
+		}
+		Ok(color * (1.0 / samples as f64))
+	}
+
+	// Share of the world light which reaches `point`, see `shadow_transmittance`
+	pub fn light_transmittance(
+		&self,
+		point: Point,
+		wavelength: Option<f64>,
+	) -> Result<Color, String> {
+		self.shadow_transmittance(point, self.light.position, wavelength)
+	}
+
+	// Colour of the light left after travelling from `target` to `point`. Every transparent object
+	// in between filters it once by its transparency and surface colour, and absorbs it along the
+	// way inside as in Beer-Lambert. Opaque objects block it entirely.
+	pub fn shadow_transmittance(
+		&self,
+		point: Point,
+		target: Point,
+		wavelength: Option<f64>,
+	) -> Result<Color, String> {
+		let v = target - point;
+		self.transmittance_along(point, v.normalise(), v.magnitude(), wavelength)
+	}
 
+	// Same as `shadow_transmittance` for light arriving along `direction` from `distance` away,
+	// which is infinite for the background
+	pub fn transmittance_along(
+		&self,
+		point: Point,
+		direction: Vector,
+		distance: f64,
+		wavelength: Option<f64>,
+	) -> Result<Color, String> {
+		let black = Color::new(0.0, 0.0, 0.0);
 		let r = Ray::new(point, direction);
-		let mut intersections = r.intersect_world(self)?;
+		let mut intersections: Vec<_> = r
+			.intersect_world(self)?
+			.into_iter()
+			.filter(|i| i.object.flags().cast_shadows && i.object.material().volume.is_none())
+			.collect();
 		intersections.sort_by(|i1, i2| (i1.t).partial_cmp(&i2.t).unwrap());
-		match hit(&intersections) {
-			Some(h) =>
-				if h.t < distance {
-					Ok(true)
-				} else {
-					Ok(false)
-				},
-			None => Ok(false),
+
+		let mut transmittance = Color::new(1.0, 1.0, 1.0);
+		let mut occluders: Vec<&dyn ConcreteShape> = vec![];
+		for i in intersections.iter().filter(|i| i.t >= 0.0 && i.t < distance) {
+			if occluders.contains(&i.object) {
+				continue
+			}
+			let material = i.object.material().spectral(wavelength);
+			// with a caustic map the light bent by refractive objects arrives through the photons
+			if material.transparency == 0.0 || self.caustics.is_some() {
+				return Ok(black)
+			}
+			occluders.push(i.object);
+			transmittance = transmittance *
+				material.color_at(i.object, &r.position(i.t)) *
+				material.transparency;
+
+			// entry and exit points pair up along the ray
+			let hits: Vec<_> =
+				intersections.iter().filter(|h| h.object == i.object).map(|h| h.t).collect();
+			let inside: f64 = hits
+				.as_chunks::<2>()
+				.0
+				.iter()
+				.map(|[enter, exit]| (exit.min(distance) - enter.max(0.0)).max(0.0))
+				.sum();
+			transmittance = transmittance * material.transmittance(inside);
 		}
+		Ok(transmittance)
 	}
 }
 
+// Deterministic seed so whitted renders stay free of frame to frame noise
+fn point_seed(point: &Point) -> u64 {
+	let p = point.tuple;
+	p.x.to_bits() ^ p.y.to_bits().rotate_left(21) ^ p.z.to_bits().rotate_left(42)
+}
+
 impl Default for World {
 	fn default() -> Self {
 		let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
@@ -133,7 +473,7 @@ impl Default for World {
 		let mut s2 = Sphere::default();
 		s2.set_transform(scaling(0.5, 0.5, 0.5));
 
-		Self { objects: vec![Box::new(s1), Box::new(s2)], light }
+		Self::new(vec![Box::new(s1), Box::new(s2)], light)
 	}
 }
 
@@ -145,6 +485,7 @@ mod tests {
 		patterns::color_pattern::ColorPattern,
 		primitives::vector::Vector,
 		shapes::{plane::Plane, shape::ConcreteShape, spheres::Sphere},
+		visualisation::{materials::Material, medium::Volume},
 	};
 
 	#[test]
@@ -238,24 +579,293 @@ mod tests {
 	}
 
 	#[test]
-	fn is_shadowed_test() {
+	fn absorption_test() {
+		let world = |size: f64, density: f64| {
+			let mut glass = Sphere::default();
+			glass.set_transform(scaling(size, size, size));
+			glass.set_material(Material {
+				ambient: 0.0,
+				diffuse: 0.0,
+				specular: 0.0,
+				transparency: 1.0,
+				refractive_index: 1.0,
+				absorption: Color::new(0.5, 0.0, 0.25),
+				absorption_density: density,
+				..Material::default()
+			});
+			let mut w = World::new(
+				vec![Box::new(glass)],
+				PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(0.0, 0.0, 0.0)),
+			);
+			w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+			w
+		};
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+		// light crosses two units of glass
+		let expected = Color::new((-1.0_f64).exp(), 1.0, (-0.5_f64).exp());
+		approx::assert_relative_eq!(
+			world(1.0, 1.0).color_at(&r, None).unwrap(),
+			expected,
+			epsilon = 1e-5
+		);
+
+		// thicker glass and denser absorption tint more
+		let expected = Color::new((-2.0_f64).exp(), 1.0, (-1.0_f64).exp());
+		approx::assert_relative_eq!(
+			world(2.0, 1.0).color_at(&r, None).unwrap(),
+			expected,
+			epsilon = 1e-5
+		);
+		approx::assert_relative_eq!(
+			world(1.0, 2.0).color_at(&r, None).unwrap(),
+			expected,
+			epsilon = 1e-5
+		);
+
+		// rays grazing the edge travel through less glass
+		let r = Ray::new(Point::new(0.0, 0.8, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let expected = Color::new((-0.6_f64).exp(), 1.0, (-0.3_f64).exp());
+		approx::assert_relative_eq!(
+			world(1.0, 1.0).color_at(&r, None).unwrap(),
+			expected,
+			epsilon = 1e-5
+		);
+	}
+
+	#[test]
+	fn test_environment_lighting() {
+		use crate::{
+			shapes::plane::Plane,
+			visualisation::{canvas::Canvas, environment::EnvironmentMap},
+		};
+
+		let r = Ray::new(Point::new(0.0, 1.0, -1.0), Vector::new(0.0, -1.0, 1.0).normalise());
+		let lighting = |w: &World, sampler: &mut Sampler, samples: usize| {
+			let xs = r.intersect_world(w).unwrap();
+			let comps = r.prepare_computations(hit(&xs).unwrap(), Some(&xs));
+			w.environment_lighting(&comps, sampler, samples).unwrap()
+		};
+
+		// a diffuse plane under a uniform white sky reflects `diffuse` of it
+		let mut w = World::new(
+			vec![Box::new(Plane::default())],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		let mut sampler = Sampler::new(1);
+		assert_eq!(lighting(&w, &mut sampler, 100), Color::new(0.0, 0.0, 0.0));
+
+		let mut canvas = Canvas::new(8, 4);
+		canvas.pixels.iter_mut().for_each(|p| *p = Color::new(1.0, 1.0, 1.0));
+		for background in [
+			Environment::Constant(Color::new(1.0, 1.0, 1.0)),
+			Environment::Equirectangular(EnvironmentMap::new(canvas).unwrap()),
+		] {
+			w.background = background;
+			let c = lighting(&w, &mut sampler, 20000);
+			approx::assert_relative_eq!(c, Color::new(0.9, 0.9, 0.9), epsilon = 0.03);
+		}
+
+		// only used by whitted shading when asked for, the black point light adds nothing
+		assert_eq!(w.color_at(&r, None).unwrap(), Color::new(0.0, 0.0, 0.0));
+		w.environment_samples = 256;
+		let c = w.color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(c, Color::new(0.9, 0.9, 0.9), epsilon = 0.1);
+
+		// shadow rays are cast against the objects
+		let mut roof = Plane::default();
+		roof.set_transform(translation(0.0, 2.0, 0.0));
+		w.objects.push(Box::new(roof));
+		assert_eq!(lighting(&w, &mut sampler, 100), Color::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn test_background() {
+		use crate::shapes::plane::Plane;
+
+		// rays which miss everything look the environment up by direction
+		let w = World {
+			background: Environment::Gradient {
+				top: Color::new(0.0, 0.0, 1.0),
+				bottom: Color::new(1.0, 1.0, 1.0),
+			},
+			..World::default()
+		};
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+		assert_eq!(w.color_at(&r, None).unwrap(), Color::new(0.0, 0.0, 1.0));
+
+		// and so do reflections
+		let mut mirror = Plane::default();
+		mirror.set_transform(translation(0.0, -1.0, 0.0));
+		let m = mirror.get_material();
+		m.color = Color::new(0.0, 0.0, 0.0);
+		m.ambient = 0.0;
+		m.reflective = 1.0;
+		let sky = Color::new(0.3, 0.6, 0.9);
+		let mut w = World::new(vec![Box::new(mirror)], w.light);
+		w.background = Environment::Constant(sky);
+		let r = Ray::new(Point::new(0.0, 0.0, -3.0), Vector::new(0.0, -1.0, 1.0).normalise());
+		approx::assert_relative_eq!(w.color_at(&r, None).unwrap(), sky, epsilon = 1e-6);
+
+		// and refractions
+		let mut glass = Sphere::default();
+		let m = glass.get_material();
+		m.color = Color::new(0.0, 0.0, 0.0);
+		m.ambient = 0.0;
+		m.diffuse = 0.0;
+		m.specular = 0.0;
+		m.transparency = 1.0;
+		m.refractive_index = 1.5;
+		w.objects = vec![Box::new(glass)];
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		approx::assert_relative_eq!(w.color_at(&r, None).unwrap(), sky, epsilon = 1e-6);
+	}
+
+	#[test]
+	fn light_transmittance_test() {
 		let w = World::default();
+		let lit = Color::new(1.0, 1.0, 1.0);
+		let shadowed = Color::new(0.0, 0.0, 0.0);
 
 		// no shadow when nothing is collinear with point and light
 		let p = Point::new(0.0, 10.0, 0.0);
-		assert!(!w.is_shadowed(p).unwrap());
+		assert_eq!(w.light_transmittance(p, None).unwrap(), lit);
 
 		// the shadow when an object is between the point and the light
 		let p = Point::new(10.0, -10.0, 10.0);
-		assert!(w.is_shadowed(p).unwrap());
+		assert_eq!(w.light_transmittance(p, None).unwrap(), shadowed);
 
 		// no shadow when an object is behind the light
 		let p = Point::new(-20.0, 20.0, -20.0);
-		assert!(!w.is_shadowed(p).unwrap());
+		assert_eq!(w.light_transmittance(p, None).unwrap(), lit);
 
 		// no shadow when an object is behind the point
 		let p = Point::new(0.0, 10.0, 0.0);
-		assert!(!w.is_shadowed(p).unwrap());
+		assert_eq!(w.light_transmittance(p, None).unwrap(), lit);
+	}
+
+	#[test]
+	fn render_flags_test() {
+		// the inner sphere shows up, in the shadow of the outer one unless it stops casting it
+		let mut w = World::default();
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		w.objects[0].get_flags().camera_visible = false;
+		approx::assert_relative_eq!(w.color_at(&r, None).unwrap(), Color::new(0.1, 0.1, 0.1));
+		w.objects[0].get_flags().cast_shadows = false;
+		assert!(w.color_at(&r, None).unwrap().red > 0.5);
+
+		// shadows are only received where wanted
+		let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
+		let mut ball = Sphere::default();
+		ball.set_transform(translation(0.0, 2.0, 0.0));
+		let mut w = World::new(vec![Box::new(Plane::default()), Box::new(ball)], light);
+		let r = Ray::new(Point::new(0.0, 0.5, -0.5), Vector::new(0.0, -1.0, 1.0).normalise());
+		approx::assert_relative_eq!(w.color_at(&r, None).unwrap(), Color::new(0.1, 0.1, 0.1));
+		w.objects[0].get_flags().receive_shadows = false;
+		let lit = w.color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(lit, Color::new(1.0, 1.0, 1.0), epsilon = 1e-5);
+
+		// and that includes emitters, the background and ambient occlusion
+		let mut lamp = Sphere::default();
+		lamp.set_transform(translation(0.0, 6.0, 0.0));
+		lamp.get_material().emissive = Color::new(1.0, 1.0, 1.0);
+		w.objects.push(Box::new(lamp));
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+		w.environment_samples = 16;
+		w.ambient_occlusion = Some(AmbientOcclusion::new(16, 100.0));
+		let unshadowed = w.color_at(&r, None).unwrap();
+		w.objects[0].get_flags().receive_shadows = true;
+		assert!(w.color_at(&r, None).unwrap().red < unshadowed.red);
+		w.objects[1].get_flags().cast_shadows = false;
+		w.objects[2].get_flags().cast_shadows = false;
+		assert_eq!(w.color_at(&r, None).unwrap(), unshadowed);
+
+		// a mirror floor reflects a ball hidden from the camera, unless it is hidden from
+		// reflections too
+		let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+		let mut mirror = Plane::default();
+		mirror.set_transform(translation(0.0, -1.0, 0.0));
+		mirror.get_material().reflective = 1.0;
+		let mut w = World::new(vec![Box::new(mirror), Box::new(Sphere::default())], light);
+		let r = Ray::new(Point::new(0.0, 0.0, -3.0), Vector::new(0.0, -1.0, 1.0).normalise());
+		let reflected = w.color_at(&r, None).unwrap();
+		w.objects[1].get_flags().camera_visible = false;
+		assert_eq!(w.color_at(&r, None).unwrap(), reflected);
+		w.objects[1].get_flags().reflection_visible = false;
+		w.objects[1].get_flags().cast_shadows = false;
+		let hidden = w.color_at(&r, None).unwrap();
+		w.objects.pop();
+		assert_ne!(hidden, reflected);
+		assert_eq!(hidden, w.color_at(&r, None).unwrap());
+	}
+
+	#[test]
+	fn coloured_shadow_test() {
+		let light = PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
+		let mut glass = Sphere::new_glass_sphere();
+		glass.set_transform(translation(0.0, 5.0, 0.0));
+		let mut w = World::new(vec![Box::new(glass)], light);
+		let p = Point::new(0.0, 0.0, 0.0);
+
+		// clear glass lets all the light through
+		assert_eq!(w.light_transmittance(p, None).unwrap(), Color::new(1.0, 1.0, 1.0));
+
+		// tinted, partly transparent glass filters it once, not once per surface
+		w.objects[0].get_material().color = Color::new(1.0, 0.5, 0.0);
+		w.objects[0].get_material().transparency = 0.5;
+		assert_eq!(w.light_transmittance(p, None).unwrap(), Color::new(0.5, 0.25, 0.0));
+
+		// absorption inside depends on the two units of glass crossed
+		w.objects[0].get_material().color = Color::new(1.0, 1.0, 1.0);
+		w.objects[0].get_material().transparency = 1.0;
+		w.objects[0].get_material().absorption = Color::new(0.5, 0.0, 0.0);
+		approx::assert_relative_eq!(
+			w.light_transmittance(p, None).unwrap(),
+			Color::new((-1.0_f64).exp(), 1.0, 1.0),
+			epsilon = 1e-9
+		);
+
+		// from inside, only the remaining unit counts
+		approx::assert_relative_eq!(
+			w.light_transmittance(Point::new(0.0, 5.0, 0.0), None).unwrap(),
+			Color::new((-0.5_f64).exp(), 1.0, 1.0),
+			epsilon = 1e-9
+		);
+
+		// and the surface below is lit through it
+		let mut floor = Plane::default();
+		floor.get_material().ambient = 0.0;
+		floor.get_material().specular = 0.0;
+		w.objects.push(Box::new(floor));
+		let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+		let c = w.color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(
+			c,
+			Color::new(0.9 * (-1.0_f64).exp(), 0.9, 0.9),
+			epsilon = 1e-9
+		);
+
+		// light from emitters goes through the glass the same way
+		let mut lamp = Sphere::default();
+		lamp.set_transform(translation(0.0, 10.0, 0.0) * scaling(0.5, 0.5, 0.5));
+		lamp.get_material().emissive = Color::new(1.0, 1.0, 1.0);
+		w.objects.push(Box::new(lamp));
+		w.objects[0].get_material().absorption = Color::new(0.0, 0.0, 0.0);
+		let floor = Plane::default();
+		let i = Intersection::new(1.0, &floor);
+		let comps = r.prepare_computations(&i, None);
+		let area = |w: &World| w.area_lighting(&comps, &mut Sampler::new(5), 16).unwrap();
+		w.objects[0].get_flags().cast_shadows = false;
+		let open = area(&w);
+		assert!(open.red > 0.0);
+		w.objects[0].get_flags().cast_shadows = true;
+		assert_eq!(area(&w), open);
+		w.objects[0].get_material().color = Color::new(1.0, 0.5, 0.0);
+		approx::assert_relative_eq!(
+			area(&w),
+			Color::new(open.red, open.green * 0.5, 0.0),
+			epsilon = 1e-9
+		);
 	}
 
 	#[test]
@@ -347,6 +957,201 @@ mod tests {
 		approx::assert_relative_eq!(c, Color::new(0.0, 0.99888, 0.04725), epsilon = 1e-4);
 	}
 
+	#[test]
+	fn fog_test() {
+		let grey = Color::new(0.5, 0.5, 0.5);
+		let w = World {
+			background: Environment::Constant(Color::new(0.0, 0.0, 1.0)),
+			fog: Some(Fog::new(grey, 0.2)),
+			..World::default()
+		};
+
+		// misses fade into the fog completely
+		let miss = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+		assert_eq!(w.color_at(&miss, None).unwrap(), grey);
+
+		// hits are blended with it over the distance travelled
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		let t = (-0.2_f64 * 4.0).exp();
+		approx::assert_relative_eq!(
+			w.color_at(&r, None).unwrap(),
+			Color::new(0.38066125, 0.4758265, 0.28549594) * t + grey * (1.0 - t),
+			epsilon = 1e-6
+		);
+	}
+
+	#[test]
+	fn volume_test() {
+		let volume_world = |albedo: Color| {
+			let mut smoke = Sphere::default();
+			smoke.set_material(Material {
+				volume: Some(Volume::new(0.5, albedo)),
+				..Material::default()
+			});
+			let mut w = World::new(
+				vec![Box::new(smoke)],
+				PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)),
+			);
+			w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+			w
+		};
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+		// a purely absorbing volume dims what lies behind it over two units
+		let w = volume_world(Color::new(0.0, 0.0, 0.0));
+		let expected = (-1.0_f64).exp();
+		approx::assert_relative_eq!(
+			w.color_at(&r, None).unwrap(),
+			Color::new(expected, expected, expected),
+			epsilon = 1e-5
+		);
+
+		// volume boundaries neither shade nor cast shadows
+		let lit = w.light_transmittance(Point::new(0.0, 0.0, 0.0), None).unwrap();
+		assert_eq!(lit, Color::new(1.0, 1.0, 1.0));
+
+		// a scattering one adds the light it sends towards the eye
+		let mut w = volume_world(Color::new(1.0, 1.0, 1.0));
+		w.background = Environment::default();
+		let lit = w.color_at(&r, None).unwrap();
+		let inside = Ray::new(Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0));
+		let (scattered, transmittance) = w.march_volume(&inside, 2.0, &*w.objects[0]).unwrap();
+		approx::assert_relative_eq!(lit, scattered, epsilon = 1e-5);
+		approx::assert_relative_eq!(transmittance, (-1.0_f64).exp(), epsilon = 1e-9);
+		assert!(lit.red > 0.0);
+
+		// blocking the light casts a shaft of shadow through the volume
+		let mut blocker = Sphere::default();
+		blocker.set_transform(translation(0.0, 5.0, 0.0) * scaling(0.5, 0.5, 0.5));
+		w.objects.push(Box::new(blocker));
+		let shadowed = w.color_at(&r, None).unwrap();
+		assert!(shadowed.red < lit.red);
+
+		// and so does a spot light pointing away
+		let mut w = volume_world(Color::new(1.0, 1.0, 1.0));
+		w.background = Environment::default();
+		w.light = PointLight::new_spot(
+			Point::new(0.0, 10.0, 0.0),
+			Color::new(1.0, 1.0, 1.0),
+			Vector::new(0.0, 1.0, 0.0),
+			0.1,
+			0.2,
+		);
+		assert_eq!(w.color_at(&r, None).unwrap(), Color::new(0.0, 0.0, 0.0));
+	}
+
+	#[test]
+	fn voxel_volume_test() {
+		use crate::{shapes::cube::Cube, visualisation::voxel_grid::VoxelGrid};
+
+		// smoke filling the upper half of a box stretched to 2x2x4
+		let grid = VoxelGrid::new(1, 2, 1, vec![0.0, 1.0]).unwrap();
+		let mut smoke = Cube::default();
+		smoke.set_transform(scaling(1.0, 1.0, 2.0));
+		smoke.set_material(Material {
+			volume: Some(Volume::new_grid(0.5, Color::new(0.0, 0.0, 0.0), grid)),
+			..Material::default()
+		});
+		let mut w = World::new(
+			vec![Box::new(smoke)],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)),
+		);
+		w.background = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+
+		// the density grid follows the shape transform, rays cross 4 units of it
+		let through = |y: f64| {
+			let r = Ray::new(Point::new(0.0, y, -5.0), Vector::new(0.0, 0.0, 1.0));
+			w.color_at(&r, None).unwrap().red
+		};
+		approx::assert_relative_eq!(through(0.9), (-2.0_f64).exp(), epsilon = 1e-5);
+		approx::assert_relative_eq!(through(-0.9), 1.0, epsilon = 1e-5);
+		// trilinear interpolation between the two voxel centres
+		approx::assert_relative_eq!(through(0.0), (-1.0_f64).exp(), epsilon = 1e-5);
+
+		let volume = w.objects[0].material().volume.clone().unwrap();
+		assert_eq!(volume.majorant(), 0.5);
+		assert_eq!(volume.density_at(&*w.objects[0], &Point::new(0.0, 0.0, 0.0)), 0.25);
+
+		// ratio tracking agrees with the marched transmittance on average
+		let mut sampler = Sampler::new(9);
+		let direction = Vector::new(0.0, 0.0, 1.0);
+		let origin = Point::new(0.0, 0.25, -2.0);
+		let n = 4000;
+		let mut sum = 0.0;
+		for _ in 0..n {
+			sum += volume.ratio_tracking(&*w.objects[0], &origin, &direction, 4.0, &mut sampler);
+		}
+		let depth = volume.optical_depth(&*w.objects[0], &origin, &direction, 4.0, 16);
+		approx::assert_relative_eq!(depth, 0.5 * 0.75 * 4.0, epsilon = 1e-9);
+		approx::assert_relative_eq!(sum / n as f64, (-depth).exp(), epsilon = 0.02);
+
+		// delta tracking collides with the same probability
+		let collisions = (0..n)
+			.filter(|_| {
+				volume
+					.delta_tracking(&*w.objects[0], &origin, &direction, 4.0, &mut sampler)
+					.is_some()
+			})
+			.count();
+		approx::assert_relative_eq!(
+			collisions as f64 / n as f64,
+			1.0 - (-depth).exp(),
+			epsilon = 0.02
+		);
+	}
+
+	#[test]
+	fn dispersion_test() {
+		use crate::visualisation::dispersion::Dispersion;
+
+		let world = |dispersion: Option<Dispersion>| {
+			let mut prism = Sphere::default();
+			prism.set_material(Material {
+				ambient: 0.0,
+				diffuse: 0.0,
+				specular: 0.0,
+				transparency: 1.0,
+				refractive_index: 1.7,
+				dispersion,
+				..Material::default()
+			});
+			let mut w = World::new(
+				vec![Box::new(prism)],
+				PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(0.0, 0.0, 0.0)),
+			);
+			w.background = Environment::Gradient {
+				top: Color::new(1.0, 1.0, 1.0),
+				bottom: Color::new(0.0, 0.0, 0.0),
+			};
+			w
+		};
+		let r = Ray::new(Point::new(0.0, 0.6, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+		// without dispersion every channel leaves in the same direction
+		let c = world(None).color_at(&r, None).unwrap();
+		approx::assert_relative_eq!(c.red, c.blue);
+
+		// with it blue bends further down than red and sees a darker part of the sky
+		let w = world(Some(Dispersion::flint_glass()));
+		let c = w.color_at(&r, None).unwrap();
+		assert!(c.red - c.blue > 0.005, "{:?}", c);
+
+		let xs = r.intersect_world(&w).unwrap();
+		let comps = r.prepare_computations(hit(&xs).unwrap(), Some(&xs));
+		assert!(comps.is_dispersive());
+		let split = comps.at_wavelength(450.0);
+		assert!(!split.is_dispersive());
+		assert_eq!(split.n2, Dispersion::flint_glass().index(450.0));
+		let refracted = w.refracted_color(&comps, None);
+		approx::assert_relative_eq!(refracted.blue, w.refracted_color(&split, None).blue);
+
+		// Fresnel reflectance depends on the channel too
+		let reflectance = comps.reflectance();
+		assert!(reflectance.blue > reflectance.red);
+		let reflectance = world(None).objects[0].material().refractive_index_at(Some(450.0));
+		assert_eq!(reflectance, 1.7);
+	}
+
 	#[test]
 	fn test_shade_hit_w_transparent_material() {
 		let mut w = World::default();
@@ -372,7 +1177,8 @@ mod tests {
 		let xs = vec![Intersection::new(2.0_f64.sqrt(), &*w.objects[2])];
 		let comps = r.prepare_computations(&xs[0], Some(&xs));
 		let color = w.shade_hit(&comps, Some(5));
-		approx::assert_relative_eq!(color, Color::new(0.93642, 0.68642, 0.68642), epsilon = 1e-4);
+		// the ball under the half transparent floor is lit through it
+		approx::assert_relative_eq!(color, Color::new(1.12547, 0.68642, 0.68642), epsilon = 1e-4);
 	}
 
 	#[test]
@@ -400,6 +1206,56 @@ mod tests {
 		approx::assert_relative_eq!(comps.schlick(), 0.48873, epsilon = 1e-4);
 	}
 
+	#[test]
+	fn test_emissive_material() {
+		// emissive surfaces show up even when nothing lights them
+		let mut lamp = Sphere::default();
+		lamp.get_material().emissive = Color::new(2.0, 2.0, 1.0);
+		lamp.get_material().ambient = 0.0;
+		lamp.get_material().diffuse = 0.0;
+		lamp.get_material().specular = 0.0;
+		let w = World::new(
+			vec![Box::new(lamp)],
+			PointLight::new(Point::new(0.0, 0.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+		let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+		assert_eq!(w.color_at(&r, None).unwrap(), Color::new(2.0, 2.0, 1.0));
+	}
+
+	#[test]
+	fn test_area_lighting() {
+		// a floor under an emissive sphere with the point light switched off
+		let mut lamp = Sphere::default();
+		lamp.set_transform(translation(0.0, 3.0, 0.0));
+		lamp.get_material().emissive = Color::new(1.0, 1.0, 1.0);
+		let mut floor = Plane::default();
+		floor.get_material().ambient = 0.0;
+		let w = World::new(
+			vec![Box::new(lamp), Box::new(floor)],
+			PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(0.0, 0.0, 0.0)),
+		);
+
+		let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+		let i = Intersection::new(1.0, &*w.objects[1]);
+		let comps = r.prepare_computations(&i, None);
+		let mut sampler = Sampler::new(0);
+		let c = w.area_lighting(&comps, &mut sampler, 20000).unwrap();
+
+		// irradiance from a uniform spherical emitter: E = PI * L * (r / d)^2
+		let expected = 0.9 * (1.0_f64 / 3.0).powi(2);
+		approx::assert_relative_eq!(c.red, expected, epsilon = 5e-3);
+
+		// an occluder between the floor and the lamp blocks the light
+		let mut w = w;
+		let mut blocker = Plane::default();
+		blocker.set_transform(translation(0.0, 1.5, 0.0));
+		w.objects.push(Box::new(blocker));
+		let i = Intersection::new(1.0, &*w.objects[1]);
+		let comps = r.prepare_computations(&i, None);
+		let c = w.area_lighting(&comps, &mut sampler, 100).unwrap();
+		assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+	}
+
 	#[test]
 	fn test_shade_hit_w_reflective_transparent_material() {
 		let mut w = World::default();
@@ -426,6 +1282,6 @@ mod tests {
 		let xs = vec![Intersection::new(2.0_f64.sqrt(), &*w.objects[2])];
 		let comps = r.prepare_computations(&xs[0], Some(&xs));
 		let color = w.shade_hit(&comps, Some(5));
-		approx::assert_relative_eq!(color, Color::new(0.93391, 0.69643, 0.69243), epsilon = 1e-4);
+		approx::assert_relative_eq!(color, Color::new(1.11500, 0.69643, 0.69243), epsilon = 1e-4);
 	}
 }